@@ -1,17 +1,69 @@
-use crate::Extract::*;
 use clap::{App, Arg};
-use csv::{ReaderBuilder, StringRecord, WriterBuilder};
+use csv::{ReaderBuilder, StringRecord};
 use regex::Regex;
 use std::{
     error::Error,
     fs::File,
-    io::{self, BufRead, BufReader},
+    io::{self, BufRead, BufReader, Read},
     num::NonZeroUsize,
     ops::Range,
 };
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
-type PositionList = Vec<Range<usize>>;
+type PositionList = Vec<Range<Index>>;
+
+// A position in a record, counted either from its start (0-based) or
+// back from its end, where `FromEnd(0)` is just past the last item.
+// Positions are resolved against each record as it is read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Index {
+    FromStart(usize),
+    FromEnd(usize),
+}
+
+impl Index {
+    fn resolve(self, len: usize) -> usize {
+        match self {
+            Index::FromStart(n) => n,
+            Index::FromEnd(n) => len.saturating_sub(n),
+        }
+    }
+}
+
+// How a line is split into fields
+#[derive(Debug)]
+pub enum Delimiter {
+    // A single byte, which is read as CSV unless `--raw`
+    Byte(u8),
+    // Any longer string
+    Text(String),
+    // Every match of a regex
+    Pattern(Regex),
+    // Runs of whitespace, ignoring any at the start or end of the line
+    Whitespace,
+}
+
+impl Delimiter {
+    fn split<'a>(&self, line: &'a str) -> Vec<&'a str> {
+        match self {
+            Delimiter::Byte(byte) => line.split(char::from(*byte)).collect(),
+            Delimiter::Text(text) => line.split(text.as_str()).collect(),
+            Delimiter::Pattern(regex) => regex.split(line).collect(),
+            Delimiter::Whitespace => line.split_whitespace().collect(),
+        }
+    }
+
+    // Fields are joined with the input delimiter when it is a fixed
+    // string, with a tab after a regex and a space after whitespace
+    fn output(&self) -> String {
+        match self {
+            Delimiter::Byte(byte) => char::from(*byte).to_string(),
+            Delimiter::Text(text) => text.clone(),
+            Delimiter::Pattern(_) => "\t".to_string(),
+            Delimiter::Whitespace => " ".to_string(),
+        }
+    }
+}
 
 #[derive(Debug)]
 pub enum Extract {
@@ -27,8 +79,47 @@ use crate::Extract::*;
 #[derive(Debug)]
 pub struct Config {
     files: Vec<String>,
-    delimiter: u8,
+    delimiter: Delimiter,
+    output_delimiter: String,
+    only_delimited: bool,
+    raw: bool,
     extract: Extract,
+    complement: bool,
+}
+
+// Keeps a copy of the bytes the CSV reader takes from the file, so
+// that a record without a delimiter can be written as it was read
+struct Recorder<R> {
+    file: R,
+    bytes: Vec<u8>,
+    // The file offset of `bytes[0]`, and of the end of the last record
+    start: u64,
+    done: u64,
+}
+
+impl<R: Read> Read for Recorder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.bytes.drain(..(self.done - self.start) as usize);
+        self.start = self.done;
+        let n = self.file.read(buf)?;
+        self.bytes.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+}
+
+impl<R> Recorder<R> {
+    // The text of the record between two file offsets, without the
+    // blank lines skipped ahead of it or its line ending
+    fn record(&mut self, first: u64, last: u64) -> String {
+        let text = &self.bytes
+            [(first - self.start) as usize..(last - self.start) as usize];
+        self.done = last;
+        let is_newline = |b: &u8| matches!(b, b'\r' | b'\n');
+        let start = text.iter().take_while(|b| is_newline(b)).count();
+        let end = text.len()
+            - text.iter().rev().take_while(|b| is_newline(b)).count();
+        String::from_utf8_lossy(&text[start..end.max(start)]).into_owned()
+    }
 }
 
 fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
@@ -43,6 +134,17 @@ pub fn get_args() -> MyResult<Config> {
         .version("0.1.0")
         .author("Ken Youens-Clark <kyclark@gmail.com>")
         .about("Rust cut")
+        .after_help(
+            "Each LIST is made up of one range, or many ranges separated \
+            by commas.\nA range is N, N-M or N- (N to the end). Either \
+            end may be negative to count\nback from the end of each line, \
+            and a negative end may follow a bare -:\n-1- is the last \
+            item, -3--2 the two before it and --2 all but the last.\n\
+            As -N alone could mean 1-N or -N-, it is rejected.\n\n\
+            Fields are written out with the input delimiter, or a tab \
+            after\n--delimiter-regex, or a space after --whitespace, \
+            unless --output-delimiter\nis given.",
+        )
         .arg(
             Arg::with_name("files")
                 .value_name("FILE")
@@ -55,43 +157,99 @@ pub fn get_args() -> MyResult<Config> {
                 .value_name("DELIMITER")
                 .short("d")
                 .long("delim")
-                .help("Field delimiter")
-                .default_value("\t"),
+                .help("Field delimiter [default: tab]"),
+        )
+        .arg(
+            Arg::with_name("delimiter_regex")
+                .value_name("REGEX")
+                .long("delimiter-regex")
+                .help("Split fields on a regular expression")
+                .conflicts_with_all(&["delimiter", "whitespace"])
+                .requires("fields"),
+        )
+        .arg(
+            Arg::with_name("whitespace")
+                .short("w")
+                .long("whitespace")
+                .help("Split fields on runs of whitespace, like awk")
+                .conflicts_with("delimiter")
+                .requires("fields"),
         )
         .arg(
             Arg::with_name("fields")
-                .value_name("FIELDS")
+                .value_name("LIST")
                 .short("f")
                 .long("fields")
+                .allow_hyphen_values(true)
                 .help("Selected fields")
                 .conflicts_with_all(&["chars", "bytes"]),
         )
         .arg(
             Arg::with_name("bytes")
-                .value_name("BYTES")
+                .value_name("LIST")
                 .short("b")
                 .long("bytes")
+                .allow_hyphen_values(true)
                 .help("Selected bytes")
                 .conflicts_with_all(&["fields", "chars"]),
         )
         .arg(
             Arg::with_name("chars")
-                .value_name("CHARS")
+                .value_name("LIST")
                 .short("c")
                 .long("chars")
+                .allow_hyphen_values(true)
                 .help("Selected characters")
                 .conflicts_with_all(&["fields", "bytes"]),
         )
+        .arg(
+            Arg::with_name("output_delimiter")
+                .value_name("STRING")
+                .long("output-delimiter")
+                .help("Field delimiter for output")
+                .requires("fields"),
+        )
+        .arg(
+            Arg::with_name("only_delimited")
+                .short("s")
+                .long("only-delimited")
+                .help("Skip lines that have no delimiter")
+                .requires("fields"),
+        )
+        .arg(
+            Arg::with_name("raw")
+                .long("raw")
+                .help("Split fields on every delimiter, without CSV quoting")
+                .requires("fields"),
+        )
+        .arg(
+            Arg::with_name("complement")
+                .long("complement")
+                .help("Select everything except the given positions"),
+        )
         .get_matches();
 
-    let delimiter = matches.value_of("delimiter").unwrap();
-    let delimiter_bytes = delimiter.as_bytes();
-    if delimiter_bytes.len() != 1 {
-        return Err(From::from(format!(
-            "--delim \"{}\" must be a single byte",
-            delimiter,
-        )));
-    }
+    let delimiter = if let Some(pattern) = matches.value_of("delimiter_regex")
+    {
+        let regex = Regex::new(pattern).map_err(|_| {
+            format!("Invalid --delimiter-regex \"{}\"", pattern)
+        })?;
+        if regex.is_match("") {
+            return Err(From::from(format!(
+                "--delimiter-regex \"{}\" must not match an empty string",
+                pattern
+            )));
+        }
+        Delimiter::Pattern(regex)
+    } else if matches.is_present("whitespace") {
+        Delimiter::Whitespace
+    } else {
+        match matches.value_of("delimiter").unwrap_or("\t") {
+            "" => return Err(From::from("--delim \"\" must not be empty")),
+            text if text.len() == 1 => Delimiter::Byte(text.as_bytes()[0]),
+            text => Delimiter::Text(text.to_string()),
+        }
+    };
 
     let fields = matches.value_of("fields").map(parse_position).transpose()?;
     let bytes = matches.value_of("bytes").map(parse_position).transpose()?;
@@ -107,10 +265,20 @@ pub fn get_args() -> MyResult<Config> {
         return Err(From::from("Must have --fields, --bytes, or --chars"));
     };
 
+    // Only a single byte delimiter can be read as CSV
+    let raw =
+        matches.is_present("raw") || !matches!(delimiter, Delimiter::Byte(_));
+
     Ok(Config {
         files: matches.values_of_lossy("files").unwrap(),
-        delimiter: *delimiter_bytes.first().unwrap(),
+        output_delimiter: matches
+            .value_of("output_delimiter")
+            .map_or_else(|| delimiter.output(), String::from),
+        delimiter,
+        only_delimited: matches.is_present("only_delimited"),
+        raw,
         extract,
+        complement: matches.is_present("complement"),
     })
 }
 
@@ -119,29 +287,60 @@ pub fn run(config: Config) -> MyResult<()> {
         match open(filename) {
             Err(err) => eprintln!("{}: {}", filename, err),
             Ok(file) => match &config.extract {
-                Fields(field_pos) => {
-                    let mut reader = ReaderBuilder::new()
-                        .delimiter(config.delimiter)
-                        .has_headers(false)
-                        .from_reader(file);
-
-                    let mut wtr = WriterBuilder::new()
-                        .delimiter(config.delimiter)
-                        .from_writer(io::stdout());
-
-                    for record in reader.records() {
-                        let record = record?;
-                        wtr.write_record(extract_fields(&record, field_pos))?;
+                Fields(field_pos) => match config.delimiter {
+                    Delimiter::Byte(delimiter) if !config.raw => {
+                        let recorder = Recorder {
+                            file,
+                            bytes: vec![],
+                            start: 0,
+                            done: 0,
+                        };
+                        let mut reader = ReaderBuilder::new()
+                            .delimiter(delimiter)
+                            .has_headers(false)
+                            .flexible(true)
+                            .from_reader(recorder);
+
+                        let mut record = StringRecord::new();
+                        while reader.read_record(&mut record)? {
+                            let first =
+                                record.position().map_or(0, |pos| pos.byte());
+                            let last = reader.position().byte();
+                            let line = reader.get_mut().record(first, last);
+                            let fields: Vec<_> = record.iter().collect();
+                            if let Some(out) =
+                                cut_fields(&line, &fields, field_pos, &config)
+                            {
+                                println!("{}", out);
+                            }
+                        }
                     }
-                }
+                    _ => {
+                        for line in file.lines() {
+                            let line = line?;
+                            let fields = config.delimiter.split(&line);
+                            if let Some(out) =
+                                cut_fields(&line, &fields, field_pos, &config)
+                            {
+                                println!("{}", out);
+                            }
+                        }
+                    }
+                },
                 Bytes(byte_pos) => {
                     for line in file.lines() {
-                        println!("{}", extract_bytes(&line?, byte_pos));
+                        println!(
+                            "{}",
+                            extract_bytes(&line?, byte_pos, config.complement)
+                        );
                     }
                 }
                 Chars(char_pos) => {
                     for line in file.lines() {
-                        println!("{}", extract_chars(&line?, char_pos));
+                        println!(
+                            "{}",
+                            extract_chars(&line?, char_pos, config.complement)
+                        );
                     }
                 }
             },
@@ -157,77 +356,226 @@ pub fn run(config: Config) -> MyResult<()> {
 // one less than the number represented by the original input.
 fn parse_index(input: &str) -> Result<usize, String> {
     let value_error = || format!("illegal list value: \"{}\"", input);
-    input.starts_with('+').then(|| Err(value_error())).unwrap_or_else(|| {
-        input
-            .parse::<NonZeroUsize>()
-            .map(|n| usize::from(n) - 1)
-            .map_err(|_| value_error())
-    })
+    if input.starts_with('+') {
+        return Err(value_error());
+    }
+    input
+        .parse::<NonZeroUsize>()
+        .map(|n| usize::from(n) - 1)
+        .map_err(|_| value_error())
+}
+
+// Parse one end of a range, which may be negative to count back from
+// the end of the record: as a bound, "-1" is the last item.
+fn parse_bound(input: &str) -> Result<Index, String> {
+    match input.strip_prefix('-') {
+        Some(n) => parse_index(n)
+            .map(|n| Index::FromEnd(n + 1))
+            .map_err(|_| format!("illegal list value: \"{}\"", input)),
+        None => parse_index(input).map(Index::FromStart),
+    }
+}
+
+// Parse a range where either end may be left open, so "5-" is 5
+// through the last, or counted from the end, so "-3-" and "-3--1" are
+// both the last three. A bare "-3" could be POSIX 1 through 3 or the
+// third from the end, so it is rejected rather than guessed at.
+fn parse_range(
+    val: &str,
+    range_regex: &Regex,
+) -> Result<Range<Index>, String> {
+    let value_error = || format!("illegal list value: \"{}\"", val);
+    let captures = range_regex.captures(val).ok_or_else(value_error)?;
+    let first = captures.get(1).map(|m| parse_bound(m.as_str()));
+    let last = captures.get(2).map(|m| parse_bound(m.as_str()));
+    let (first, last) = match (first.transpose()?, last.transpose()?) {
+        (None, None) => return Err(value_error()),
+        (None, Some(Index::FromStart(n))) => {
+            let first = match n {
+                0 => "1".to_string(),
+                n => format!("1-{}", n + 1),
+            };
+            return Err(format!(
+                "{}: use \"{}\" to count from the start \
+                or \"{}-\" to count from the end",
+                value_error(),
+                first,
+                val
+            ));
+        }
+        bounds => bounds,
+    };
+
+    let order_error = |n1: String, n2: String| {
+        format!(
+            "First number in range ({}) \
+            must be lower than second number ({})",
+            n1, n2
+        )
+    };
+    match (first, last) {
+        (Some(Index::FromStart(n1)), Some(Index::FromStart(n2)))
+            if n1 >= n2 =>
+        {
+            return Err(order_error(
+                (n1 + 1).to_string(),
+                (n2 + 1).to_string(),
+            ));
+        }
+        (Some(Index::FromEnd(n1)), Some(Index::FromEnd(n2))) if n1 <= n2 => {
+            return Err(order_error(format!("-{}", n1), format!("-{}", n2)));
+        }
+        _ => {}
+    }
+
+    let start = first.unwrap_or(Index::FromStart(0));
+    let end = match last {
+        None => Index::FromEnd(0),
+        Some(Index::FromStart(n)) => Index::FromStart(n + 1),
+        Some(Index::FromEnd(n)) => Index::FromEnd(n - 1),
+    };
+    Ok(start..end)
 }
 
 // takes a string `"1-5","6-8"` and returns a Vec<Range>
 fn parse_position(range: &str) -> MyResult<PositionList> {
-    let range_regex = Regex::new(r"^(\d+)-(\d+)$").unwrap();
+    let range_regex = Regex::new(r"^(-?\d+)?-(-?\d+)?$").unwrap();
     range
         .split(',')
-        .into_iter()
         .map(|val| {
-            parse_index(val).map(|n| n..n + 1).or_else(|e| {
-                range_regex.captures(val).ok_or(e).and_then(|captures| {
-                    let n1 = parse_index(&captures[1])?;
-                    let n2 = parse_index(&captures[2])?;
-                    if n1 >= n2 {
-                        return Err(format!(
-                            "First number in range ({}) \
-                            must be lower than second number ({})",
-                            n1 + 1,
-                            n2 + 1
-                        ));
-                    }
-                    Ok(n1..n2 + 1)
-                })
-            })
+            parse_index(val)
+                .map(|n| Index::FromStart(n)..Index::FromStart(n + 1))
+                .or_else(|_| parse_range(val, &range_regex))
         })
         .collect::<Result<_, _>>()
         .map_err(From::from)
 }
 
-fn extract_chars(line: &str, char_pos: &[Range<usize>]) -> String {
-    let chars: Vec<_> = line.chars().collect();
-    char_pos
+// Resolves `pos` against a record of `len` items. With `complement`,
+// selects every position below `len` that is not in `pos`, in
+// ascending order. Positions past the end select nothing, so they
+// leave the complement untouched.
+fn select(
+    pos: &[Range<Index>],
+    len: usize,
+    complement: bool,
+) -> Vec<Range<usize>> {
+    let resolved = pos
         .iter()
-        .cloned()
+        .map(|range| range.start.resolve(len)..range.end.resolve(len));
+    if !complement {
+        return resolved.collect();
+    }
+    let mut keep = vec![true; len];
+    for range in resolved {
+        let range = range.start.min(len)..range.end.min(len);
+        if !range.is_empty() {
+            keep[range].iter_mut().for_each(|k| *k = false);
+        }
+    }
+    keep.iter()
+        .enumerate()
+        .filter(|(_, &k)| k)
+        .map(|(i, _)| i..i + 1)
+        .collect()
+}
+
+fn extract_chars(
+    line: &str,
+    char_pos: &[Range<Index>],
+    complement: bool,
+) -> String {
+    let chars: Vec<_> = line.chars().collect();
+    select(char_pos, chars.len(), complement)
+        .into_iter()
         .flat_map(|range| range.filter_map(|i| chars.get(i)))
         .collect()
 }
 
-fn extract_bytes(line: &str, byte_pos: &[Range<usize>]) -> String {
+fn extract_bytes(
+    line: &str,
+    byte_pos: &[Range<Index>],
+    complement: bool,
+) -> String {
     let bytes = line.as_bytes();
-    let selected: Vec<_> = byte_pos
-        .iter()
-        .cloned()
+    let selected: Vec<_> = select(byte_pos, bytes.len(), complement)
+        .into_iter()
         .flat_map(|range| range.filter_map(|i| bytes.get(i)).copied())
         .collect();
     String::from_utf8_lossy(&selected).into_owned()
 }
 
 fn extract_fields<'a>(
-    record: &'a StringRecord,
-    field_pos: &[Range<usize>],
+    fields: &[&'a str],
+    field_pos: &[Range<Index>],
+    complement: bool,
 ) -> Vec<&'a str> {
-    field_pos
-        .iter()
-        .cloned()
-        .flat_map(|range| range.filter_map(|i| record.get(i)))
+    select(field_pos, fields.len(), complement)
+        .into_iter()
+        .flat_map(|range| range.filter_map(|i| fields.get(i)).copied())
         .collect()
 }
 
+// Selects and joins the fields of one line. As with cut, a line with
+// no delimiter passes through just as it was read, or is skipped with
+// `only_delimited`.
+fn cut_fields(
+    line: &str,
+    fields: &[&str],
+    field_pos: &[Range<Index>],
+    config: &Config,
+) -> Option<String> {
+    match fields {
+        [] | [_] if config.only_delimited => None,
+        [] | [_] => Some(line.to_string()),
+        _ => {
+            let selected =
+                extract_fields(fields, field_pos, config.complement);
+            Some(join_fields(&selected, &config.output_delimiter, !config.raw))
+        }
+    }
+}
+
+// With `quote`, fields are written the way the csv crate would write
+// them, so any that hold the delimiter, a quote or a line break are
+// quoted and an empty line is written as `""`.
+fn join_fields(fields: &[&str], delimiter: &str, quote: bool) -> String {
+    if !quote {
+        return fields.join(delimiter);
+    }
+    if matches!(fields, [] | [""]) {
+        return "\"\"".to_string();
+    }
+    let needs_quotes = |field: &str| {
+        (!delimiter.is_empty() && field.contains(delimiter))
+            || field.contains(['"', '\r', '\n'])
+    };
+    fields
+        .iter()
+        .map(|&field| {
+            if needs_quotes(field) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(delimiter)
+}
+
 #[cfg(test)]
+#[allow(clippy::single_range_in_vec_init)]
 mod unit_tests {
     use super::{
-        extract_bytes, extract_chars, extract_fields, parse_position,
+        extract_bytes, extract_chars, extract_fields, join_fields,
+        parse_position, Delimiter, Index::*, PositionList,
     };
-    use csv::StringRecord;
+    use regex::Regex;
+    use std::ops::Range;
+
+    fn pos(ranges: &[Range<usize>]) -> PositionList {
+        ranges.iter().map(|r| FromStart(r.start)..FromStart(r.end)).collect()
+    }
 
     #[test]
     fn test_parse_position() {
@@ -295,15 +643,45 @@ mod unit_tests {
         let res = parse_position("1,");
         assert!(res.is_err());
 
-        let res = parse_position("1-");
-        assert!(res.is_err());
-
         let res = parse_position("1-1-1");
         assert!(res.is_err());
 
         let res = parse_position("1-1-a");
         assert!(res.is_err());
 
+        let res = parse_position("--");
+        assert!(res.is_err());
+
+        let res = parse_position("-0");
+        assert_eq!(res.unwrap_err().to_string(), "illegal list value: \"0\"");
+
+        let res = parse_position("-0-");
+        assert_eq!(res.unwrap_err().to_string(), "illegal list value: \"-0\"");
+
+        let res = parse_position("-+1-");
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "illegal list value: \"-+1-\""
+        );
+
+        // A bare negative number is ambiguous
+        let res = parse_position("-1");
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "illegal list value: \"-1\": use \"1\" to count from the \
+            start or \"-1-\" to count from the end"
+        );
+
+        let res = parse_position("2,-3");
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "illegal list value: \"-3\": use \"1-3\" to count from the \
+            start or \"-3-\" to count from the end"
+        );
+
+        let res = parse_position("-a");
+        assert_eq!(res.unwrap_err().to_string(), "illegal list value: \"-a\"");
+
         // First number must be less than second
         let res = parse_position("1-1");
         assert!(res.is_err());
@@ -319,70 +697,237 @@ mod unit_tests {
             "First number in range (2) must be lower than second number (1)"
         );
 
+        let res = parse_position("-1--1");
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "First number in range (-1) must be lower than second number (-1)"
+        );
+
+        let res = parse_position("-1--3");
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "First number in range (-1) must be lower than second number (-3)"
+        );
+
         // All the following are acceptable
         let res = parse_position("1");
         assert!(res.is_ok());
-        assert_eq!(res.unwrap(), vec![0..1]);
+        assert_eq!(res.unwrap(), pos(&[0..1]));
 
         let res = parse_position("01");
         assert!(res.is_ok());
-        assert_eq!(res.unwrap(), vec![0..1]);
+        assert_eq!(res.unwrap(), pos(&[0..1]));
 
         let res = parse_position("1,3");
         assert!(res.is_ok());
-        assert_eq!(res.unwrap(), vec![0..1, 2..3]);
+        assert_eq!(res.unwrap(), pos(&[0..1, 2..3]));
 
         let res = parse_position("001,0003");
         assert!(res.is_ok());
-        assert_eq!(res.unwrap(), vec![0..1, 2..3]);
+        assert_eq!(res.unwrap(), pos(&[0..1, 2..3]));
 
         let res = parse_position("1-3");
         assert!(res.is_ok());
-        assert_eq!(res.unwrap(), vec![0..3]);
+        assert_eq!(res.unwrap(), pos(&[0..3]));
 
         let res = parse_position("0001-03");
         assert!(res.is_ok());
-        assert_eq!(res.unwrap(), vec![0..3]);
+        assert_eq!(res.unwrap(), pos(&[0..3]));
 
         let res = parse_position("1,7,3-5");
         assert!(res.is_ok());
-        assert_eq!(res.unwrap(), vec![0..1, 6..7, 2..5]);
+        assert_eq!(res.unwrap(), pos(&[0..1, 6..7, 2..5]));
 
         let res = parse_position("15,19-20");
         assert!(res.is_ok());
-        assert_eq!(res.unwrap(), vec![14..15, 18..20]);
+        assert_eq!(res.unwrap(), pos(&[14..15, 18..20]));
+
+        // Open-ended ranges
+        let res = parse_position("5-");
+        assert_eq!(res.unwrap(), vec![FromStart(4)..FromEnd(0)]);
+
+        // Negative numbers count from the end
+        let res = parse_position("-1-");
+        assert_eq!(res.unwrap(), vec![FromEnd(1)..FromEnd(0)]);
+
+        let res = parse_position("-3--1");
+        assert_eq!(res.unwrap(), vec![FromEnd(3)..FromEnd(0)]);
+
+        let res = parse_position("2--2,--3");
+        assert_eq!(
+            res.unwrap(),
+            vec![FromStart(1)..FromEnd(1), FromStart(0)..FromEnd(2)]
+        );
     }
 
     #[test]
     fn test_extract_fields() {
-        let rec = StringRecord::from(vec!["Captain", "Sham", "12345"]);
-        assert_eq!(extract_fields(&rec, &[0..1]), &["Captain"]);
-        assert_eq!(extract_fields(&rec, &[1..2]), &["Sham"]);
-        assert_eq!(extract_fields(&rec, &[0..1, 2..3]), &["Captain", "12345"]);
-        assert_eq!(extract_fields(&rec, &[0..1, 3..4]), &["Captain"]);
-        assert_eq!(extract_fields(&rec, &[1..2, 0..1]), &["Sham", "Captain"]);
+        let rec = ["Captain", "Sham", "12345"];
+        assert_eq!(extract_fields(&rec, &pos(&[0..1]), false), &["Captain"]);
+        assert_eq!(extract_fields(&rec, &pos(&[1..2]), false), &["Sham"]);
+        assert_eq!(
+            extract_fields(&rec, &pos(&[0..1, 2..3]), false),
+            &["Captain", "12345"]
+        );
+        assert_eq!(
+            extract_fields(&rec, &pos(&[0..1, 3..4]), false),
+            &["Captain"]
+        );
+        assert_eq!(
+            extract_fields(&rec, &pos(&[1..2, 0..1]), false),
+            &["Sham", "Captain"]
+        );
+
+        // The complement keeps the original order and ignores
+        // positions past the end of the record
+        assert_eq!(
+            extract_fields(&rec, &pos(&[1..2]), true),
+            &["Captain", "12345"]
+        );
+        assert_eq!(extract_fields(&rec, &pos(&[2..3, 0..1]), true), &["Sham"]);
+        assert_eq!(extract_fields(&rec, &pos(&[1..6]), true), &["Captain"]);
+        assert_eq!(
+            extract_fields(&rec, &pos(&[3..4]), true),
+            &["Captain", "Sham", "12345"]
+        );
+        assert!(extract_fields(&rec, &pos(&[0..3]), true).is_empty());
+
+        // Negative and open-ended ranges are resolved per record
+        let last = [FromEnd(1)..FromEnd(0)];
+        assert_eq!(extract_fields(&rec, &last, false), &["12345"]);
+        assert_eq!(extract_fields(&rec, &last, true), &["Captain", "Sham"]);
+        let rest = [FromStart(1)..FromEnd(0)];
+        assert_eq!(extract_fields(&rec, &rest, false), &["Sham", "12345"]);
+        let rec = ["a", "b"];
+        assert_eq!(extract_fields(&rec, &last, false), &["b"]);
+        let many = [FromEnd(5)..FromEnd(1)];
+        assert_eq!(extract_fields(&rec, &many, false), &["a"]);
+        let middle = [FromStart(1)..FromEnd(1)];
+        assert!(extract_fields(&rec, &middle, false).is_empty());
+        assert_eq!(extract_fields(&rec, &middle, true), &["a", "b"]);
+    }
+
+    #[test]
+    fn test_delimiter() {
+        let byte = Delimiter::Byte(b',');
+        assert_eq!(byte.split("a,b,,c"), ["a", "b", "", "c"]);
+        assert_eq!(byte.output(), ",");
+
+        let text = Delimiter::Text(" | ".to_string());
+        assert_eq!(text.split("a | b|c | "), ["a", "b|c", ""]);
+        assert_eq!(text.output(), " | ");
+
+        let pattern = Delimiter::Pattern(Regex::new(r"\s*;\s*").unwrap());
+        assert_eq!(pattern.split("a ; b;c"), ["a", "b", "c"]);
+        assert_eq!(pattern.output(), "\t");
+
+        let whitespace = Delimiter::Whitespace;
+        assert_eq!(whitespace.split("  a \t b  "), ["a", "b"]);
+        assert!(whitespace.split("   ").is_empty());
+        assert_eq!(whitespace.output(), " ");
+    }
+
+    #[test]
+    fn test_join_fields() {
+        assert_eq!(join_fields(&["a", "b"], ",", false), "a,b");
+        assert_eq!(join_fields(&["a", "b"], " | ", false), "a | b");
+        assert_eq!(join_fields(&["a,b", "c"], ",", false), "a,b,c");
+        assert_eq!(join_fields(&[], ",", false), "");
+
+        // Quoted the way the csv crate writes records
+        assert_eq!(join_fields(&["a,b", "c"], ",", true), "\"a,b\",c");
+        assert_eq!(join_fields(&["a,b", "c"], "\t", true), "a,b\tc");
+        assert_eq!(join_fields(&["a::b", "c"], "::", true), "\"a::b\"::c");
+        assert_eq!(
+            join_fields(&["say \"hi\""], ",", true),
+            "\"say \"\"hi\"\"\""
+        );
+        assert_eq!(join_fields(&[], ",", true), "\"\"");
+        assert_eq!(join_fields(&[""], ",", true), "\"\"");
+        assert_eq!(join_fields(&["", ""], ",", true), ",");
     }
 
     #[test]
     fn test_extract_chars() {
-        assert_eq!(extract_chars("", &[0..1]), "".to_string());
-        assert_eq!(extract_chars("ábc", &[0..1]), "á".to_string());
-        assert_eq!(extract_chars("ábc", &[0..1, 2..3]), "ác".to_string());
-        assert_eq!(extract_chars("ábc", &[0..3]), "ábc".to_string());
-        assert_eq!(extract_chars("ábc", &[2..3, 1..2]), "cb".to_string());
+        assert_eq!(extract_chars("", &pos(&[0..1]), false), "".to_string());
+        assert_eq!(
+            extract_chars("ábc", &pos(&[0..1]), false),
+            "á".to_string()
+        );
+        assert_eq!(
+            extract_chars("ábc", &pos(&[0..1, 2..3]), false),
+            "ác".to_string()
+        );
         assert_eq!(
-            extract_chars("ábc", &[0..1, 1..2, 4..5]),
+            extract_chars("ábc", &pos(&[0..3]), false),
+            "ábc".to_string()
+        );
+        assert_eq!(
+            extract_chars("ábc", &pos(&[2..3, 1..2]), false),
+            "cb".to_string()
+        );
+        assert_eq!(
+            extract_chars("ábc", &pos(&[0..1, 1..2, 4..5]), false),
             "áb".to_string()
         );
+        assert_eq!(
+            extract_chars("ábc", &pos(&[1..2]), true),
+            "ác".to_string()
+        );
+        assert_eq!(
+            extract_chars("ábc", &pos(&[2..3, 0..1]), true),
+            "b".to_string()
+        );
+        assert_eq!(
+            extract_chars("ábc", &pos(&[0..1, 0..2]), true),
+            "c".to_string()
+        );
+        assert_eq!(
+            extract_chars("ábc", &pos(&[5..6]), true),
+            "ábc".to_string()
+        );
+        assert_eq!(extract_chars("", &pos(&[0..1]), true), "".to_string());
+        let last_two = [FromEnd(2)..FromEnd(0)];
+        assert_eq!(extract_chars("ábc", &last_two, false), "bc".to_string());
+        assert_eq!(extract_chars("á", &last_two, false), "á".to_string());
     }
 
     #[test]
     fn test_extract_bytes() {
-        assert_eq!(extract_bytes("ábc", &[0..1]), "�".to_string());
-        assert_eq!(extract_bytes("ábc", &[0..2]), "á".to_string());
-        assert_eq!(extract_bytes("ábc", &[0..3]), "áb".to_string());
-        assert_eq!(extract_bytes("ábc", &[0..4]), "ábc".to_string());
-        assert_eq!(extract_bytes("ábc", &[3..4, 2..3]), "cb".to_string());
-        assert_eq!(extract_bytes("ábc", &[0..2, 5..6]), "á".to_string());
+        assert_eq!(
+            extract_bytes("ábc", &pos(&[0..1]), false),
+            "�".to_string()
+        );
+        assert_eq!(
+            extract_bytes("ábc", &pos(&[0..2]), false),
+            "á".to_string()
+        );
+        assert_eq!(
+            extract_bytes("ábc", &pos(&[0..3]), false),
+            "áb".to_string()
+        );
+        assert_eq!(
+            extract_bytes("ábc", &pos(&[0..4]), false),
+            "ábc".to_string()
+        );
+        assert_eq!(
+            extract_bytes("ábc", &pos(&[3..4, 2..3]), false),
+            "cb".to_string()
+        );
+        assert_eq!(
+            extract_bytes("ábc", &pos(&[0..2, 5..6]), false),
+            "á".to_string()
+        );
+        assert_eq!(
+            extract_bytes("ábc", &pos(&[0..2]), true),
+            "bc".to_string()
+        );
+        assert_eq!(
+            extract_bytes("ábc", &pos(&[3..4, 1..2]), true),
+            "�b".to_string()
+        );
+        assert_eq!(extract_bytes("ábc", &pos(&[2..9]), true), "á".to_string());
+        let first = [FromEnd(4)..FromEnd(2)];
+        assert_eq!(extract_bytes("ábc", &first, false), "á".to_string());
     }
 }
@@ -1,3 +1,5 @@
+#![allow(clippy::needless_borrows_for_generic_args)]
+
 use assert_cmd::Command;
 use predicates::prelude::*;
 use rand::{distributions::Alphanumeric, Rng};
@@ -9,6 +11,9 @@ const PRG: &str = "cutr";
 const CSV: &str = "tests/inputs/movies1.csv";
 const TSV: &str = "tests/inputs/movies1.tsv";
 const BOOKS: &str = "tests/inputs/books.tsv";
+const MIXED: &str = "tests/inputs/movies3.csv";
+const LOG: &str = "tests/inputs/access.log";
+const SPACED: &str = "tests/inputs/spaced.txt";
 
 // --------------------------------------------------
 fn random_string() -> String {
@@ -82,13 +87,35 @@ fn dies_bad_digit_chars() -> TestResult {
 // --------------------------------------------------
 #[test]
 fn dies_empty_delimiter() -> TestResult {
-    dies(&[CSV, "-f", "1", "-d", ""], "--delim \"\" must be a single byte")
+    dies(&[CSV, "-f", "1", "-d", ""], "--delim \"\" must not be empty")
 }
 
 // --------------------------------------------------
 #[test]
-fn dies_bad_delimiter() -> TestResult {
-    dies(&[CSV, "-f", "1", "-d", ",,"], "--delim \",,\" must be a single byte")
+fn dies_bad_delimiter_regex() -> TestResult {
+    dies(
+        &[CSV, "-f", "1", "--delimiter-regex", "("],
+        "Invalid --delimiter-regex \"(\"",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_empty_delimiter_regex() -> TestResult {
+    dies(
+        &[CSV, "-f", "1", "--delimiter-regex", " *"],
+        "--delimiter-regex \" *\" must not match an empty string",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_whitespace_delimiter() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args([CSV, "-f", "1", "-w", "-d", ","])
+        .assert()
+        .failure();
+    Ok(())
 }
 
 // --------------------------------------------------
@@ -318,3 +345,184 @@ fn tsv_c1_8() -> TestResult {
 fn repeated_value() -> TestResult {
     run(&[BOOKS, "-c", "1,1"], "tests/expected/books.c1,1.out")
 }
+
+// --------------------------------------------------
+#[test]
+fn tsv_f2_complement() -> TestResult {
+    run(
+        &[TSV, "-f", "2", "--complement"],
+        "tests/expected/movies1.tsv.f2.complement.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn csv_f1_3_complement() -> TestResult {
+    run(
+        &[CSV, "-f", "1,3", "-d", ",", "--complement"],
+        "tests/expected/movies1.csv.f1,3.dcomma.complement.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn tsv_b1_8_complement() -> TestResult {
+    run(
+        &[TSV, "-b", "1-8", "--complement"],
+        "tests/expected/movies1.tsv.b1-8.complement.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn books_c2_5_complement() -> TestResult {
+    run(
+        &[BOOKS, "-c", "2-5", "--complement"],
+        "tests/expected/books.c2-5.complement.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn tsv_f_last() -> TestResult {
+    run(&[TSV, "-f", "-1-"], "tests/expected/movies1.tsv.f-1-.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bare_negative() -> TestResult {
+    // "-1" alone could be the first field or the last
+    dies(
+        &[TSV, "-f", "-1"],
+        "illegal list value: \"-1\": use \"1\" to count from the start \
+        or \"-1-\" to count from the end",
+    )?;
+    dies(&[TSV, "-b", "-3"], "use \"1-3\" to count from the start")
+}
+
+// --------------------------------------------------
+#[test]
+fn csv_f_negative_range() -> TestResult {
+    run(
+        &[CSV, "-f", "-3--2", "-d", ","],
+        "tests/expected/movies1.csv.f-3--2.dcomma.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn books_c3_to_end() -> TestResult {
+    run(&[BOOKS, "-c", "3-"], "tests/expected/books.c3-.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_negative_range() -> TestResult {
+    dies(
+        &[CSV, "-f", "-1--2"],
+        "First number in range (-1) must be lower than second number (-2)",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn tsv_output_delimiter() -> TestResult {
+    run(
+        &[TSV, "-f", "3,1", "--output-delimiter", ","],
+        "tests/expected/movies1.tsv.f3,1.outdelim.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn csv_output_delimiter() -> TestResult {
+    run(
+        &[MIXED, "-d", ",", "-f", "1,3", "--output-delimiter", " | "],
+        "tests/expected/movies3.csv.f1,3.outdelim.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn csv_only_delimited() -> TestResult {
+    run(
+        &[MIXED, "-d", ",", "-f", "1,3", "-s"],
+        "tests/expected/movies3.csv.f1,3.s.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn raw_output_delimiter() -> TestResult {
+    run(
+        &[MIXED, "-d", ",", "-f", "1,3", "--raw", "--output-delimiter", " | "],
+        "tests/expected/movies3.csv.f1,3.raw.outdelim.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn raw_only_delimited() -> TestResult {
+    run(
+        &[MIXED, "-d", ",", "-f", "1,3", "--raw", "--only-delimited"],
+        "tests/expected/movies3.csv.f1,3.raw.s.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn undelimited_lines_pass_through() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-d", ",", "-f", "2"])
+        .write_stdin("\"abc\"\r\n\n  spaced  \n\"x\",\"y\"\n\"a\nb\"\n")
+        .assert()
+        .success()
+        .stdout("\"abc\"\n  spaced  \ny\n\"a\nb\"\n");
+    Command::cargo_bin(PRG)?
+        .args(["-w", "-f", "2"])
+        .write_stdin("  \"lone\"\t\n a  b \n")
+        .assert()
+        .success()
+        .stdout("  \"lone\"\t\nb\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_only_delimited_chars() -> TestResult {
+    Command::cargo_bin(PRG)?.args([CSV, "-c", "1", "-s"]).assert().failure();
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn log_string_delimiter() -> TestResult {
+    run(
+        &[LOG, "-d", " | ", "-f", "1,-1-"],
+        "tests/expected/access.log.f1,-1-.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn log_delimiter_regex() -> TestResult {
+    run(
+        &[LOG, "--delimiter-regex", r" *\| *", "-f", "2-3", "-s"],
+        "tests/expected/access.log.f2-3.regex.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn spaced_whitespace() -> TestResult {
+    run(&[SPACED, "-w", "-f", "1,3"], "tests/expected/spaced.txt.f1,3.w.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn spaced_whitespace_output_delimiter() -> TestResult {
+    run(
+        &[SPACED, "-w", "-f", "2-", "--output-delimiter", ","],
+        "tests/expected/spaced.txt.f2-.w.outdelim.out",
+    )
+}
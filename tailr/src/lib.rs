@@ -1,16 +1,33 @@
+#![allow(clippy::obfuscated_if_else)]
+
 use crate::TakeValue::*;
+use ansi_term::Colour;
+use chrono::{
+    DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone,
+    Utc,
+};
 use clap::{App, Arg};
 use once_cell::sync::OnceCell;
-use regex::Regex;
+use regex::{bytes::Regex as BytesRegex, Regex};
 use std::{
     error::Error,
     fs::File,
-    io::{self, BufRead, BufReader, Read, Seek, SeekFrom},
+    io::{
+        self, BufRead, BufReader, BufWriter, IsTerminal, Read, Seek, SeekFrom,
+        Write,
+    },
+    thread,
+    time::Duration as StdDuration,
 };
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
 static NUM_REGEX: OnceCell<Regex> = OnceCell::new();
+static DURATION_REGEX: OnceCell<Regex> = OnceCell::new();
+static UNIT_REGEX: OnceCell<Regex> = OnceCell::new();
+static RFC3339_REGEX: OnceCell<Regex> = OnceCell::new();
+static SYSLOG_REGEX: OnceCell<Regex> = OnceCell::new();
+static CLF_REGEX: OnceCell<Regex> = OnceCell::new();
 
 #[derive(Debug, PartialEq)]
 enum TakeValue {
@@ -24,6 +41,55 @@ pub struct Config {
     lines: TakeValue,
     bytes: Option<TakeValue>,
     quiet: bool,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    time_format: Option<String>,
+    follow: bool,
+    filter: LineFilter,
+}
+
+// Selects and colors lines on their way to the output
+#[derive(Debug, Default)]
+struct LineFilter {
+    grep: Option<BytesRegex>,
+    grep_v: Option<BytesRegex>,
+    highlight: Option<BytesRegex>,
+    color: bool,
+}
+
+impl LineFilter {
+    // Match against the line without its line ending
+    fn is_match(&self, line: &[u8]) -> bool {
+        let text = trim_line_ending(line);
+        self.grep.as_ref().is_none_or(|re| re.is_match(text))
+            && self.grep_v.as_ref().is_none_or(|re| !re.is_match(text))
+    }
+
+    fn write_line(&self, line: &[u8], out: &mut impl Write) -> io::Result<()> {
+        match &self.highlight {
+            Some(re) if self.color => {
+                let style = Colour::Red.bold();
+                let mut last = 0;
+                for m in re.find_iter(trim_line_ending(line)) {
+                    if m.start() == m.end() {
+                        continue;
+                    }
+                    out.write_all(&line[last..m.start()])?;
+                    write!(out, "{}", style.prefix())?;
+                    out.write_all(m.as_bytes())?;
+                    write!(out, "{}", style.suffix())?;
+                    last = m.end();
+                }
+                out.write_all(&line[last..])
+            }
+            _ => out.write_all(line),
+        }
+    }
+}
+
+fn trim_line_ending(line: &[u8]) -> &[u8] {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    line.strip_suffix(b"\r").unwrap_or(line)
 }
 
 pub fn get_args() -> MyResult<Config> {
@@ -60,6 +126,54 @@ pub fn get_args() -> MyResult<Config> {
                 .long("quiet")
                 .help("Suppress headers"),
         )
+        .arg(
+            Arg::with_name("since")
+                .long("since")
+                .value_name("TIME")
+                .conflicts_with_all(&["lines", "bytes"])
+                .help("Print lines timestamped at or after TIME"),
+        )
+        .arg(
+            Arg::with_name("until")
+                .long("until")
+                .value_name("TIME")
+                .conflicts_with_all(&["lines", "bytes"])
+                .help("Print lines timestamped at or before TIME"),
+        )
+        .arg(
+            Arg::with_name("time_format")
+                .long("time-format")
+                .value_name("FORMAT")
+                .help("strftime format of the line timestamps"),
+        )
+        .arg(
+            Arg::with_name("follow")
+                .short("f")
+                .long("follow")
+                .conflicts_with("until")
+                .help("Output appended data as the files grow"),
+        )
+        .arg(
+            Arg::with_name("grep")
+                .long("grep")
+                .value_name("REGEX")
+                .conflicts_with("bytes")
+                .help("Only print lines matching REGEX"),
+        )
+        .arg(
+            Arg::with_name("grep_v")
+                .long("grep-v")
+                .value_name("REGEX")
+                .conflicts_with("bytes")
+                .help("Only print lines not matching REGEX"),
+        )
+        .arg(
+            Arg::with_name("highlight")
+                .long("highlight")
+                .value_name("REGEX")
+                .conflicts_with("bytes")
+                .help("Color matches of REGEX on a terminal"),
+        )
         .get_matches();
 
     let lines = matches
@@ -74,14 +188,50 @@ pub fn get_args() -> MyResult<Config> {
         .transpose()
         .map_err(|e| format!("illegal byte count -- {}", e))?;
 
+    let time_format = matches.value_of("time_format").map(String::from);
+    let now = Utc::now();
+    let parse = |val| parse_time(val, now, time_format.as_deref());
+
+    let since = matches
+        .value_of("since")
+        .map(parse)
+        .transpose()
+        .map_err(|e| format!("illegal time -- {}", e))?;
+
+    let until = matches
+        .value_of("until")
+        .map(parse)
+        .transpose()
+        .map_err(|e| format!("illegal time -- {}", e))?;
+
+    let filter = LineFilter {
+        grep: matches.value_of("grep").map(parse_pattern).transpose()?,
+        grep_v: matches.value_of("grep_v").map(parse_pattern).transpose()?,
+        highlight: matches
+            .value_of("highlight")
+            .map(parse_pattern)
+            .transpose()?,
+        color: io::stdout().is_terminal(),
+    };
+
     Ok(Config {
         files: matches.values_of_lossy("files").unwrap(),
         lines: lines.unwrap(),
         bytes,
         quiet: matches.is_present("quiet"),
+        since,
+        until,
+        time_format,
+        follow: matches.is_present("follow"),
+        filter,
     })
 }
 
+fn parse_pattern(pattern: &str) -> MyResult<BytesRegex> {
+    BytesRegex::new(pattern)
+        .map_err(|_| format!("Invalid pattern \"{}\"", pattern).into())
+}
+
 // accepts any "integer" value
 // optionally prefixed with "+" or "-"
 #[allow(unused)]
@@ -150,55 +300,373 @@ fn parse_num(val: &str) -> MyResult<TakeValue> {
     }
 }
 
+// Parse a `--since`/`--until` value, either a duration before `now`
+// such as "15m" or "1h30m", or an absolute time
+fn parse_time(
+    val: &str,
+    now: DateTime<Utc>,
+    time_format: Option<&str>,
+) -> MyResult<DateTime<Utc>> {
+    let duration_regex = DURATION_REGEX
+        .get_or_init(|| Regex::new(r"^(?:\d+[smhdw])+$").unwrap());
+
+    if duration_regex.is_match(val) {
+        let unit_regex =
+            UNIT_REGEX.get_or_init(|| Regex::new(r"(\d+)([smhdw])").unwrap());
+        let mut duration = Duration::zero();
+        for caps in unit_regex.captures_iter(val) {
+            let num: i64 = caps[1].parse().map_err(|_| val)?;
+            let unit = match &caps[2] {
+                "s" => Duration::try_seconds(num),
+                "m" => Duration::try_minutes(num),
+                "h" => Duration::try_hours(num),
+                "d" => Duration::try_days(num),
+                _ => Duration::try_weeks(num),
+            };
+            duration = unit
+                .and_then(|unit| duration.checked_add(&unit))
+                .ok_or(val)?;
+        }
+        return now.checked_sub_signed(duration).ok_or_else(|| val.into());
+    }
+
+    if let Ok(dt) = DateTime::parse_from_rfc3339(val) {
+        return Ok(dt.with_timezone(&Utc));
+    }
+
+    let naive =
+        ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%d %H:%M"]
+            .iter()
+            .find_map(|fmt| NaiveDateTime::parse_from_str(val, fmt).ok())
+            .or_else(|| {
+                NaiveDate::parse_from_str(val, "%Y-%m-%d")
+                    .ok()
+                    .and_then(|date| date.and_hms_opt(0, 0, 0))
+            });
+
+    naive
+        .and_then(from_local)
+        .or_else(|| parse_timestamp(val, time_format, now.year()))
+        .ok_or_else(|| val.into())
+}
+
+// Interpret a timestamp without a UTC offset as local time
+fn from_local(naive: NaiveDateTime) -> Option<DateTime<Utc>> {
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+// Find the timestamp in a line of a log, using either the given
+// strftime format or, by default, syslog, RFC 3339 or Apache CLF.
+// Syslog timestamps have no year, so `year` is assumed.
+fn parse_timestamp(
+    line: &str,
+    time_format: Option<&str>,
+    year: i32,
+) -> Option<DateTime<Utc>> {
+    if let Some(fmt) = time_format {
+        return parse_custom_timestamp(line, fmt);
+    }
+
+    let syslog_regex = SYSLOG_REGEX.get_or_init(|| {
+        Regex::new(r"^([A-Z][a-z]{2} [ \d]\d \d{2}:\d{2}:\d{2})").unwrap()
+    });
+    let rfc3339_regex = RFC3339_REGEX.get_or_init(|| {
+        Regex::new(
+            r"(\d{4}-\d{2}-\d{2})[T ](\d{2}:\d{2}:\d{2}(?:\.\d+)?)(Z|[+-]\d{2}:?\d{2})?",
+        )
+        .unwrap()
+    });
+    let clf_regex = CLF_REGEX.get_or_init(|| {
+        Regex::new(
+            r"\[(\d{2}/[A-Z][a-z]{2}/\d{4}:\d{2}:\d{2}:\d{2} [+-]\d{4})\]",
+        )
+        .unwrap()
+    });
+
+    if let Some(caps) = syslog_regex.captures(line) {
+        let stamp = format!("{} {}", year, &caps[1]);
+        return NaiveDateTime::parse_from_str(&stamp, "%Y %b %e %H:%M:%S")
+            .ok()
+            .and_then(from_local);
+    }
+
+    if let Some(caps) = rfc3339_regex.captures(line) {
+        let naive = format!("{}T{}", &caps[1], &caps[2]);
+        return match caps.get(3) {
+            Some(offset) => {
+                DateTime::parse_from_rfc3339(&(naive + offset.as_str()))
+                    .ok()
+                    .map(|dt| dt.with_timezone(&Utc))
+            }
+            None => {
+                NaiveDateTime::parse_from_str(&naive, "%Y-%m-%dT%H:%M:%S%.f")
+                    .ok()
+                    .and_then(from_local)
+            }
+        };
+    }
+
+    clf_regex.captures(line).and_then(|caps| {
+        DateTime::parse_from_str(&caps[1], "%d/%b/%Y:%H:%M:%S %z")
+            .ok()
+            .map(|dt| dt.with_timezone(&Utc))
+    })
+}
+
+// Try the format at the start of the line and after
+// every space or "[" until a timestamp parses
+fn parse_custom_timestamp(line: &str, fmt: &str) -> Option<DateTime<Utc>> {
+    std::iter::once(0)
+        .chain(line.match_indices([' ', '[']).map(|(i, _)| i + 1))
+        .filter_map(|i| line.get(i..))
+        .find_map(|text| {
+            DateTime::parse_and_remainder(text, fmt)
+                .ok()
+                .map(|(dt, _)| dt.with_timezone(&Utc))
+                .or_else(|| {
+                    NaiveDateTime::parse_and_remainder(text, fmt)
+                        .ok()
+                        .and_then(|(naive, _)| from_local(naive))
+                })
+        })
+}
+
+// Binary search the byte offsets of a chronologically sorted
+// file for the start of the first line stamped at or after `since`
+fn find_since_offset<T>(
+    file: &mut T,
+    since: DateTime<Utc>,
+    time_format: Option<&str>,
+    year: i32,
+) -> MyResult<u64>
+where
+    T: BufRead + Seek,
+{
+    let mut low = 0;
+    let mut high = file.seek(SeekFrom::End(0))?;
+    let mut buf = Vec::new();
+    while low < high {
+        let mid = low + (high - low) / 2;
+
+        // skip to the first line that starts at or after `mid`
+        let mut pos = if mid == 0 {
+            file.seek(SeekFrom::Start(0))?
+        } else {
+            file.seek(SeekFrom::Start(mid - 1))?;
+            buf.clear();
+            mid - 1 + file.read_until(b'\n', &mut buf)? as u64
+        };
+
+        // then read on until a line has a timestamp
+        let mut stamp = None;
+        loop {
+            buf.clear();
+            let bytes_read = file.read_until(b'\n', &mut buf)?;
+            if bytes_read == 0 {
+                break;
+            }
+            pos += bytes_read as u64;
+            let line = String::from_utf8_lossy(&buf);
+            if let Some(ts) = parse_timestamp(&line, time_format, year) {
+                stamp = Some(ts);
+                break;
+            }
+        }
+
+        // everything up to the end of an earlier line can be skipped
+        match stamp {
+            Some(ts) if ts < since => low = pos,
+            _ => high = mid,
+        }
+    }
+
+    Ok(low)
+}
+
+// Print the lines stamped between `since` and `until`.
+// Lines without a timestamp, such as stack traces, belong
+// to the closest stamped line above them.
+fn print_time_range<T>(
+    mut file: T,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    time_format: Option<&str>,
+    filter: &LineFilter,
+    out: &mut impl Write,
+) -> MyResult<()>
+where
+    T: BufRead + Seek,
+{
+    let year = Local::now().year();
+    let start = match since {
+        Some(since) => find_since_offset(&mut file, since, time_format, year)?,
+        None => 0,
+    };
+    file.seek(SeekFrom::Start(start))?;
+
+    let mut current = None;
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        let bytes_read = file.read_until(b'\n', &mut buf)?;
+        if bytes_read == 0 {
+            break;
+        }
+        let line = String::from_utf8_lossy(&buf);
+        if let Some(ts) = parse_timestamp(&line, time_format, year) {
+            if until.is_some_and(|until| ts > until) {
+                break;
+            }
+            current = Some(ts);
+        }
+        if current.is_some_and(|ts| since.is_none_or(|since| ts >= since))
+            && filter.is_match(&buf)
+        {
+            filter.write_line(&buf, out)?;
+        }
+    }
+
+    Ok(())
+}
+
 fn print_bytes<T>(
     mut file: T,
     num_bytes: &TakeValue,
     total_bytes: i64,
+    out: &mut impl Write,
 ) -> MyResult<()>
 where
     T: Read + Seek,
 {
     if let Some(start) = get_start_index(num_bytes, total_bytes) {
+        // stream the raw bytes rather than holding the tail in memory
         file.seek(SeekFrom::Start(start))?;
-        let mut buffer = Vec::new();
-        file.read_to_end(&mut buffer)?;
-        if !buffer.is_empty() {
-            print!("{}", String::from_utf8_lossy(&buffer));
-        }
+        io::copy(&mut file, out)?;
     }
 
     Ok(())
 }
 
 pub fn run(config: Config) -> MyResult<()> {
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
     let num_files = config.files.len();
+    let mut followed = vec![];
     for (file_num, filename) in config.files.iter().enumerate() {
-        match File::open(&filename) {
+        match File::open(filename) {
             Err(err) => eprintln!("{}: {}", filename, err),
             Ok(file) => {
                 if !config.quiet && num_files > 1 {
-                    println!(
-                        "{}==> {} <==",
-                        if file_num > 0 { "\n" } else { "" },
-                        filename,
-                    );
+                    print_header(filename, file_num > 0, &mut out)?;
                 }
-                let (total_lines, total_bytes) = count_lines_bytes(&filename)?;
-
-                let file = BufReader::new(file);
+                let mut file = BufReader::new(file);
 
-                if let Some(num_bytes) = &config.bytes {
-                    print_bytes(file, num_bytes, total_bytes)?;
+                if config.since.is_some() || config.until.is_some() {
+                    print_time_range(
+                        &mut file,
+                        config.since,
+                        config.until,
+                        config.time_format.as_deref(),
+                        &config.filter,
+                        &mut out,
+                    )?;
                 } else {
-                    print_lines(file, &config.lines, total_lines)?;
+                    let (total_lines, total_bytes) =
+                        count_lines_bytes(filename)?;
+
+                    if let Some(num_bytes) = &config.bytes {
+                        print_bytes(
+                            &mut file,
+                            num_bytes,
+                            total_bytes,
+                            &mut out,
+                        )?;
+                    } else {
+                        print_lines(
+                            &mut file,
+                            &config.lines,
+                            total_lines,
+                            &config.filter,
+                            &mut out,
+                        )?;
+                    }
+                }
+
+                if config.follow {
+                    file.seek(SeekFrom::End(0))?;
+                    followed.push((filename.as_str(), file));
                 }
             }
         }
     }
+    out.flush()?;
+
+    if !followed.is_empty() {
+        let headers = !config.quiet && num_files > 1;
+        follow(&mut followed, &config.filter, headers, &mut out)?;
+    }
 
     Ok(())
 }
 
+fn print_header(
+    filename: &str,
+    separate: bool,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    writeln!(out, "{}==> {} <==", if separate { "\n" } else { "" }, filename)
+}
+
+// Poll the files for appended lines until interrupted,
+// printing a header whenever the output switches files.
+// A file that shrinks is assumed to be truncated and is
+// read again from the start.
+fn follow(
+    files: &mut [(&str, BufReader<File>)],
+    filter: &LineFilter,
+    headers: bool,
+    out: &mut impl Write,
+) -> MyResult<()> {
+    let mut last_printed = files.len() - 1;
+    let mut partial: Vec<Vec<u8>> = vec![Vec::new(); files.len()];
+    loop {
+        let mut idle = true;
+        for (file_num, (filename, file)) in files.iter_mut().enumerate() {
+            let len = file.get_ref().metadata()?.len();
+            if len < file.stream_position()? {
+                file.seek(SeekFrom::Start(0))?;
+                partial[file_num].clear();
+            }
+
+            // `read_until` keeps appending to an unfinished line
+            // until the rest of it has been written
+            let buf = &mut partial[file_num];
+            while file.read_until(b'\n', buf)? > 0 {
+                if !buf.ends_with(b"\n") {
+                    break;
+                }
+                idle = false;
+                if filter.is_match(buf) {
+                    if headers && file_num != last_printed {
+                        print_header(filename, true, out)?;
+                        last_printed = file_num;
+                    }
+                    filter.write_line(buf, out)?;
+                }
+                buf.clear();
+            }
+        }
+        out.flush()?;
+
+        if idle {
+            thread::sleep(StdDuration::from_secs(1));
+        }
+    }
+}
+
 // Find the starting line's index using the num
 // of lines the user wants to print and the total
 // number of lines in the file.
@@ -240,6 +708,8 @@ fn print_lines(
     mut file: impl BufRead,
     num_lines: &TakeValue,
     total_lines: i64,
+    filter: &LineFilter,
+    out: &mut impl Write,
 ) -> MyResult<()> {
     if let Some(start) = get_start_index(num_lines, total_lines) {
         let mut line_num = 0;
@@ -249,8 +719,8 @@ fn print_lines(
             if bytes_read == 0 {
                 break;
             }
-            if line_num >= start {
-                print!("{}", String::from_utf8_lossy(&buf));
+            if line_num >= start && filter.is_match(&buf) {
+                filter.write_line(&buf, out)?;
             }
             line_num += 1;
             buf.clear();
@@ -282,7 +752,13 @@ fn count_lines_bytes(filename: &str) -> MyResult<(i64, i64)> {
 
 #[cfg(test)]
 mod tests {
-    use super::{count_lines_bytes, get_start_index, parse_num, TakeValue::*};
+    use super::{
+        count_lines_bytes, find_since_offset, from_local, get_start_index,
+        parse_num, parse_time, parse_timestamp, LineFilter, TakeValue::*,
+    };
+    use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+    use regex::bytes::Regex;
+    use std::io::Cursor;
 
     #[test]
     fn test_parse_num() {
@@ -383,4 +859,181 @@ mod tests {
         // return 0 to print the whole file
         assert_eq!(get_start_index(&TakeNum(-20), 10), Some(0));
     }
+
+    #[test]
+    fn test_parse_time() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap();
+
+        // Durations are counted back from now
+        let res = parse_time("15m", now, None);
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), now - Duration::minutes(15));
+
+        let res = parse_time("1h30m", now, None);
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), now - Duration::minutes(90));
+
+        let res = parse_time("2d", now, None);
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), now - Duration::days(2));
+
+        // RFC 3339 keeps its offset
+        let res = parse_time("2024-03-01T10:00:00+01:00", now, None);
+        assert!(res.is_ok());
+        assert_eq!(
+            res.unwrap(),
+            Utc.with_ymd_and_hms(2024, 3, 1, 9, 0, 0).unwrap()
+        );
+
+        // Times without an offset are local
+        let naive = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let res = parse_time("2024-03-01 10:30", now, None);
+        assert!(res.is_ok());
+        assert_eq!(
+            Some(res.unwrap()),
+            from_local(naive.and_hms_opt(10, 30, 0).unwrap())
+        );
+
+        let res = parse_time("2024-03-01", now, None);
+        assert!(res.is_ok());
+        assert_eq!(
+            Some(res.unwrap()),
+            from_local(naive.and_hms_opt(0, 0, 0).unwrap())
+        );
+
+        // Anything else is invalid
+        let res = parse_time("15x", now, None);
+        assert!(res.is_err());
+        assert_eq!(res.unwrap_err().to_string(), "15x");
+
+        let res = parse_time("yesterday", now, None);
+        assert!(res.is_err());
+        assert_eq!(res.unwrap_err().to_string(), "yesterday");
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        let expected = Utc.with_ymd_and_hms(2024, 3, 1, 10, 0, 0).unwrap();
+
+        // RFC 3339
+        let res = parse_timestamp("2024-03-01T10:00:00Z started", None, 2024);
+        assert_eq!(res, Some(expected));
+
+        let res = parse_timestamp(
+            "[worker] 2024-03-01 11:00:00.250+01:00 started",
+            None,
+            2024,
+        );
+        assert_eq!(res, Some(expected + Duration::milliseconds(250)));
+
+        // Apache CLF
+        let res = parse_timestamp(
+            r#"10.0.0.1 - - [01/Mar/2024:05:00:00 -0500] "GET / HTTP/1.1""#,
+            None,
+            2024,
+        );
+        assert_eq!(res, Some(expected));
+
+        // Syslog takes the year from the caller
+        let res =
+            parse_timestamp("Mar  1 10:00:00 host sshd[42]: ok", None, 2024);
+        let naive = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        assert_eq!(res, from_local(naive.and_hms_opt(10, 0, 0).unwrap()));
+
+        // A custom format can appear after any space
+        let res = parse_timestamp(
+            "job=1 at 01.03.2024 10:00:00 +0000 done",
+            Some("%d.%m.%Y %H:%M:%S %z"),
+            2024,
+        );
+        assert_eq!(res, Some(expected));
+
+        // Lines without a timestamp
+        assert_eq!(parse_timestamp("    at handler", None, 2024), None);
+        assert_eq!(
+            parse_timestamp("2024-03-01T10:00:00Z", Some("%d.%m.%Y"), 2024),
+            None
+        );
+    }
+
+    #[test]
+    fn test_find_since_offset() {
+        let log = "2024-03-01T10:00:00Z a\n\
+                   2024-03-01T10:05:00Z b\n\
+                   \tcontinued\n\
+                   2024-03-01T10:10:00Z c\n\
+                   2024-03-01T10:15:00Z d\n";
+        let since = |h, m| Utc.with_ymd_and_hms(2024, 3, 1, h, m, 0).unwrap();
+        let find = |ts: DateTime<Utc>| {
+            find_since_offset(&mut Cursor::new(log), ts, None, 2024).unwrap()
+        };
+
+        // Before the first line
+        assert_eq!(find(since(9, 0)), 0);
+        assert_eq!(find(since(10, 0)), 0);
+
+        // An exact match or the next line after a gap
+        assert_eq!(find(since(10, 5)), 23);
+        assert_eq!(find(since(10, 15)), 80);
+
+        // Unstamped lines may be included, but they belong
+        // to an earlier line and are not printed
+        assert_eq!(find(since(10, 7)), 46);
+
+        // After the last line
+        assert_eq!(find(since(11, 0)), log.len() as u64);
+
+        // Empty files
+        assert_eq!(
+            find_since_offset(&mut Cursor::new(""), since(10, 0), None, 2024)
+                .unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_line_filter() {
+        // No patterns let everything through
+        let filter = LineFilter::default();
+        assert!(filter.is_match(b"anything\n"));
+
+        let filter = LineFilter {
+            grep: Some(Regex::new("(?i)error").unwrap()),
+            grep_v: Some(Regex::new("retrying$").unwrap()),
+            ..Default::default()
+        };
+        assert!(filter.is_match(b"ERROR disk full\n"));
+        assert!(!filter.is_match(b"INFO disk ok\n"));
+
+        // The line ending is not part of the match
+        assert!(!filter.is_match(b"error: timeout, retrying\r\n"));
+
+        // Raw bytes are matched too
+        assert!(filter.is_match(b"error \xff\xfe\n"));
+    }
+
+    #[test]
+    fn test_line_filter_highlight() {
+        let write = |filter: &LineFilter, line: &[u8]| {
+            let mut out = vec![];
+            filter.write_line(line, &mut out).unwrap();
+            out
+        };
+
+        // Only color when writing to a terminal
+        let mut filter = LineFilter {
+            highlight: Some(Regex::new("o+").unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(write(&filter, b"foo bar\n"), b"foo bar\n");
+
+        filter.color = true;
+        assert_eq!(
+            write(&filter, b"foo boo\n"),
+            b"f\x1b[1;31moo\x1b[0m b\x1b[1;31moo\x1b[0m\n"
+        );
+
+        // Lines without a match are left alone
+        assert_eq!(write(&filter, b"bar\xff\n"), b"bar\xff\n");
+    }
 }
@@ -1,8 +1,11 @@
+#![allow(clippy::needless_borrows_for_generic_args)]
+
 use assert_cmd::Command;
 use predicates::prelude::*;
 use rand::{distributions::Alphanumeric, Rng};
 use std::fs::{self, File};
 use std::io::Read;
+use std::time::Duration;
 
 type TestResult = Result<(), Box<dyn std::error::Error>>;
 
@@ -12,6 +15,10 @@ const ONE: &str = "tests/inputs/one.txt";
 const TWO: &str = "tests/inputs/two.txt";
 const THREE: &str = "tests/inputs/three.txt";
 const TEN: &str = "tests/inputs/ten.txt";
+const LATIN1: &str = "tests/inputs/latin1.txt";
+const LOG: &str = "tests/inputs/log.txt";
+const ACCESS: &str = "tests/inputs/access.log";
+const CUSTOM: &str = "tests/inputs/custom.log";
 
 // --------------------------------------------------
 fn random_string() -> String {
@@ -86,6 +93,61 @@ fn dies_bytes_and_lines() -> TestResult {
     Ok(())
 }
 
+// --------------------------------------------------
+#[test]
+fn dies_bad_since() -> TestResult {
+    let bad = random_string();
+    let expected = format!("illegal time -- {}", &bad);
+    Command::cargo_bin(PRG)?
+        .args(["--since", &bad, LOG])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(expected));
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_since_and_lines() -> TestResult {
+    let msg = "The argument '--lines <LINES>' cannot be \
+               used with '--since <TIME>'";
+
+    Command::cargo_bin(PRG)?
+        .args(["--since", "15m", "-n", "1", LOG])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(msg));
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_grep() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--grep", "*foo", LOG])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(r#"Invalid pattern "*foo""#));
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn follows_filtered_lines() -> TestResult {
+    // Follow mode never exits on its own
+    let output = Command::cargo_bin(PRG)?
+        .args(["-f", "-n", "+1", "--grep", "GET", LOG])
+        .timeout(Duration::from_secs(2))
+        .output()?;
+    let expected = fs::read("tests/expected/log.txt.grep.out")?;
+    assert_eq!(output.stdout, expected);
+
+    Ok(())
+}
+
 // --------------------------------------------------
 #[test]
 fn skips_bad_file() -> TestResult {
@@ -101,16 +163,15 @@ fn skips_bad_file() -> TestResult {
 
 // --------------------------------------------------
 fn run(args: &[&str], expected_file: &str) -> TestResult {
-    // Extra work here due to lossy UTF
+    // Compare raw bytes as the output may not be valid UTF-8
     let mut file = File::open(expected_file)?;
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer)?;
-    let expected = String::from_utf8_lossy(&buffer);
+    let mut expected = Vec::new();
+    file.read_to_end(&mut expected)?;
 
     Command::cargo_bin(PRG)?
         .args(args)
         .assert()
-        .stdout(predicate::eq(expected.as_bytes() as &[u8]));
+        .stdout(predicate::eq(expected.as_slice()));
 
     Ok(())
 }
@@ -805,3 +866,112 @@ fn multiple_files_c_plus_3() -> TestResult {
         "tests/expected/all.c+3.out",
     )
 }
+
+#[test]
+fn log_since() -> TestResult {
+    run(
+        &["--since", "2024-03-01T10:10:00Z", LOG],
+        "tests/expected/log.txt.since.out",
+    )
+}
+
+#[test]
+fn log_since_between_lines() -> TestResult {
+    run(
+        &["--since", "2024-03-01T10:07:30Z", LOG],
+        "tests/expected/log.txt.since.out",
+    )
+}
+
+#[test]
+fn log_until() -> TestResult {
+    run(
+        &["--until", "2024-03-01T10:10:00Z", LOG],
+        "tests/expected/log.txt.until.out",
+    )
+}
+
+#[test]
+fn log_since_until() -> TestResult {
+    run(
+        &[
+            "--since",
+            "2024-03-01T10:10:00Z",
+            "--until",
+            "2024-03-01T10:15:00Z",
+            LOG,
+        ],
+        "tests/expected/log.txt.since.until.out",
+    )
+}
+
+#[test]
+fn log_since_relative() -> TestResult {
+    // Every line is years old
+    run(&["--since", "15m", LOG], "tests/expected/empty.txt.out")
+}
+
+#[test]
+fn access_log_since() -> TestResult {
+    run(
+        &["--since", "2024-03-01T10:10:00+00:00", ACCESS],
+        "tests/expected/access.log.since.out",
+    )
+}
+
+#[test]
+fn custom_log_since_until() -> TestResult {
+    run(
+        &[
+            "--time-format",
+            "%d.%m.%Y %H:%M:%S %z",
+            "--since",
+            "2024-03-01T10:05:00Z",
+            "--until",
+            "2024-03-01T10:10:00Z",
+            CUSTOM,
+        ],
+        "tests/expected/custom.log.since.until.out",
+    )
+}
+
+#[test]
+fn latin1_n2() -> TestResult {
+    run(&[LATIN1, "-n", "2"], "tests/expected/latin1.txt.n2.out")
+}
+
+#[test]
+fn latin1_c12() -> TestResult {
+    run(&[LATIN1, "-c", "12"], "tests/expected/latin1.txt.c12.out")
+}
+
+#[test]
+fn log_grep() -> TestResult {
+    run(&[LOG, "--grep", "GET"], "tests/expected/log.txt.grep.out")
+}
+
+#[test]
+fn log_grep_v() -> TestResult {
+    run(&[LOG, "--grep-v", "^ |GET"], "tests/expected/log.txt.grep-v.out")
+}
+
+#[test]
+fn log_n3_grep() -> TestResult {
+    run(
+        &[LOG, "-n", "3", "--grep", "GET"],
+        "tests/expected/log.txt.n3.grep.out",
+    )
+}
+
+#[test]
+fn log_since_grep() -> TestResult {
+    run(
+        &["--since", "2024-03-01T10:10:00Z", "--grep", "GET", LOG],
+        "tests/expected/log.txt.n3.grep.out",
+    )
+}
+
+#[test]
+fn log_highlight_not_a_tty() -> TestResult {
+    run(&[LOG, "--highlight", "GET"], "tests/expected/log.txt.out")
+}
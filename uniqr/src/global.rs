@@ -0,0 +1,404 @@
+use crate::MyResult;
+use std::{
+    cmp::Reverse,
+    collections::{hash_map::Entry as MapEntry, BinaryHeap, HashMap},
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    mem,
+};
+use xxhash_rust::xxh3::{xxh3_128, xxh3_64, xxh3_64_with_seed};
+
+// Number of files a table is split into when it spills to disk
+const FAN_OUT: usize = 64;
+
+// Partitions deeper than this are kept in memory regardless
+const MAX_DEPTH: u64 = 4;
+
+// Rough cost of a table entry besides the strings it owns
+const ENTRY_OVERHEAD: usize =
+    mem::size_of::<Key>() + mem::size_of::<Entry>() + 16;
+
+// Size of the digests stored by `--hash`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HashBits {
+    Bits64,
+    Bits128,
+}
+
+// Which line of a group is printed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Keep {
+    First,
+    Last,
+}
+
+#[derive(Debug, Hash, PartialEq, Eq)]
+enum Key {
+    Text(Vec<u8>),
+    Digest(u128),
+}
+
+// `line` is None once the line has been printed, so that
+// streaming output does not keep every line in memory
+#[derive(Debug)]
+struct Entry {
+    seq: u64,
+    count: u64,
+    line: Option<Vec<u8>>,
+}
+
+// Removes repeated lines anywhere in the input. Without counts
+// the first line of a group is printed as soon as it is seen;
+// otherwise the kept lines are printed by `finish` in the order
+// they appeared.
+#[derive(Debug)]
+pub struct Dedup {
+    hash: Option<HashBits>,
+    max_memory: Option<usize>,
+    keep: Keep,
+    buffered: bool,
+    seq: u64,
+    table: Table,
+    spilled: Option<Partitions>,
+}
+
+impl Dedup {
+    pub fn new(
+        hash: Option<HashBits>,
+        max_memory: Option<usize>,
+        counting: bool,
+        keep: Keep,
+    ) -> Self {
+        Dedup {
+            hash,
+            max_memory,
+            keep,
+            buffered: counting || keep == Keep::Last,
+            seq: 0,
+            table: Table::new(keep),
+            spilled: None,
+        }
+    }
+
+    // Returns whether `line` should be printed right away
+    pub fn insert(&mut self, key: &[u8], line: &[u8]) -> MyResult<bool> {
+        let key = match self.hash {
+            Some(HashBits::Bits64) => Key::Digest(xxh3_64(key).into()),
+            Some(HashBits::Bits128) => Key::Digest(xxh3_128(key)),
+            None => Key::Text(key.to_vec()),
+        };
+        let seq = self.seq;
+        self.seq += 1;
+
+        // Once spilled, every line waits for `finish`
+        if let Some(partitions) = &mut self.spilled {
+            let entry = Entry { seq, count: 1, line: Some(line.to_vec()) };
+            partitions.write(&key, &entry)?;
+            return Ok(false);
+        }
+
+        let line = self.buffered.then(|| line.to_vec());
+        let is_new = self.table.add(key, Entry { seq, count: 1, line });
+
+        if self.max_memory.is_some_and(|max| self.table.memory > max) {
+            let mut partitions = Partitions::new(0)?;
+            partitions.dump(&mut self.table)?;
+            self.spilled = Some(partitions);
+        }
+
+        Ok(is_new && !self.buffered)
+    }
+
+    // Print the lines still waiting, with their total counts
+    pub fn finish(
+        self,
+        mut print: impl FnMut(u64, &[u8]) -> MyResult<()>,
+    ) -> MyResult<()> {
+        match self.spilled {
+            None => {
+                for (count, line) in self.table.into_sorted() {
+                    print(count, &line)?;
+                }
+                Ok(())
+            }
+            Some(partitions) => {
+                let max_memory = self.max_memory.unwrap_or(usize::MAX);
+                let results = partitions.resolve(max_memory, self.keep)?;
+                merge_results(results, |_, count, line| print(count, line))
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Table {
+    map: HashMap<Key, Entry>,
+    memory: usize,
+    keep: Keep,
+}
+
+impl Table {
+    fn new(keep: Keep) -> Self {
+        Table { map: HashMap::new(), memory: 0, keep }
+    }
+
+    // Combine an entry with any other one for the same key,
+    // keeping the line that appeared first or last
+    fn add(&mut self, key: Key, entry: Entry) -> bool {
+        let keep = self.keep;
+        match self.map.entry(key) {
+            MapEntry::Occupied(mut existing) => {
+                let existing = existing.get_mut();
+                existing.count += entry.count;
+                let replace = match keep {
+                    Keep::First => entry.seq < existing.seq,
+                    Keep::Last => entry.seq > existing.seq,
+                };
+                if replace {
+                    self.memory -= line_memory(&existing.line);
+                    self.memory += line_memory(&entry.line);
+                    existing.seq = entry.seq;
+                    existing.line = entry.line;
+                }
+                false
+            }
+            MapEntry::Vacant(vacant) => {
+                self.memory += ENTRY_OVERHEAD
+                    + key_memory(vacant.key())
+                    + line_memory(&entry.line);
+                vacant.insert(entry);
+                true
+            }
+        }
+    }
+
+    // The unprinted lines and their counts in input order
+    fn into_sorted(self) -> Vec<(u64, Vec<u8>)> {
+        let mut entries: Vec<_> = self
+            .map
+            .into_values()
+            .filter_map(|e| e.line.map(|line| (e.seq, e.count, line)))
+            .collect();
+        entries.sort_unstable_by_key(|(seq, _, _)| *seq);
+        entries.into_iter().map(|(_, count, line)| (count, line)).collect()
+    }
+}
+
+fn key_memory(key: &Key) -> usize {
+    match key {
+        Key::Text(text) => text.capacity(),
+        Key::Digest(_) => 0,
+    }
+}
+
+fn line_memory(line: &Option<Vec<u8>>) -> usize {
+    line.as_ref().map_or(0, Vec::capacity)
+}
+
+// Temporary files holding the entries of a table split by key hash,
+// so that each file can be deduplicated on its own
+#[derive(Debug)]
+struct Partitions {
+    depth: u64,
+    files: Vec<BufWriter<File>>,
+}
+
+impl Partitions {
+    fn new(depth: u64) -> io::Result<Self> {
+        let files = (0..FAN_OUT)
+            .map(|_| tempfile::tempfile().map(BufWriter::new))
+            .collect::<io::Result<_>>()?;
+        Ok(Partitions { depth, files })
+    }
+
+    fn write(&mut self, key: &Key, entry: &Entry) -> io::Result<()> {
+        let hash = match key {
+            Key::Text(text) => xxh3_64_with_seed(text, self.depth),
+            Key::Digest(digest) => {
+                xxh3_64_with_seed(&digest.to_le_bytes(), self.depth)
+            }
+        };
+        let file = &mut self.files[(hash % FAN_OUT as u64) as usize];
+        write_record(file, key, entry)
+    }
+
+    fn dump(&mut self, table: &mut Table) -> io::Result<()> {
+        for (key, entry) in table.map.drain() {
+            self.write(&key, &entry)?;
+        }
+        table.memory = 0;
+        Ok(())
+    }
+
+    // Deduplicate each partition, splitting any that are still too
+    // big, into result files that are each sorted by input order
+    fn resolve(self, max_memory: usize, keep: Keep) -> MyResult<Vec<File>> {
+        let mut results = vec![];
+        for file in self.files {
+            let mut file = file.into_inner().map_err(|e| e.into_error())?;
+            file.seek(SeekFrom::Start(0))?;
+            let depth = self.depth + 1;
+            results.push(resolve_partition(file, depth, max_memory, keep)?);
+        }
+        Ok(results)
+    }
+}
+
+fn resolve_partition(
+    file: File,
+    depth: u64,
+    max_memory: usize,
+    keep: Keep,
+) -> MyResult<File> {
+    let mut input = BufReader::new(file);
+    let mut table = Table::new(keep);
+    let mut spilled: Option<Partitions> = None;
+    while let Some((key, entry)) = read_record(&mut input)? {
+        if let Some(partitions) = &mut spilled {
+            partitions.write(&key, &entry)?;
+            continue;
+        }
+        table.add(key, entry);
+        if table.memory > max_memory
+            && table.map.len() > 1
+            && depth < MAX_DEPTH
+        {
+            let mut partitions = Partitions::new(depth)?;
+            partitions.dump(&mut table)?;
+            spilled = Some(partitions);
+        }
+    }
+
+    let mut out = BufWriter::new(tempfile::tempfile()?);
+    match spilled {
+        None => {
+            let mut entries: Vec<_> = table.map.into_values().collect();
+            entries.sort_unstable_by_key(|e| e.seq);
+            for entry in entries.iter().filter(|e| e.line.is_some()) {
+                write_entry(&mut out, entry)?;
+            }
+        }
+        Some(partitions) => {
+            let results = partitions.resolve(max_memory, keep)?;
+            merge_results(results, |seq, count, line| {
+                let line = Some(line.to_vec());
+                Ok(write_entry(&mut out, &Entry { seq, count, line })?)
+            })?;
+        }
+    }
+
+    let mut file = out.into_inner().map_err(|e| e.into_error())?;
+    file.seek(SeekFrom::Start(0))?;
+    Ok(file)
+}
+
+// Merge result files that are each sorted by input order
+fn merge_results(
+    files: Vec<File>,
+    mut print: impl FnMut(u64, u64, &[u8]) -> MyResult<()>,
+) -> MyResult<()> {
+    let mut inputs: Vec<_> = files.into_iter().map(BufReader::new).collect();
+    let mut heads = vec![];
+    let mut heap = BinaryHeap::new();
+    for (i, input) in inputs.iter_mut().enumerate() {
+        let head = read_result(input)?;
+        if let Some(entry) = &head {
+            heap.push(Reverse((entry.seq, i)));
+        }
+        heads.push(head);
+    }
+
+    while let Some(Reverse((_, i))) = heap.pop() {
+        if let Some(entry) = heads[i].take() {
+            print(
+                entry.seq,
+                entry.count,
+                entry.line.as_deref().unwrap_or_default(),
+            )?;
+        }
+        heads[i] = read_result(&mut inputs[i])?;
+        if let Some(entry) = &heads[i] {
+            heap.push(Reverse((entry.seq, i)));
+        }
+    }
+    Ok(())
+}
+
+fn write_record(
+    out: &mut impl Write,
+    key: &Key,
+    entry: &Entry,
+) -> io::Result<()> {
+    match key {
+        Key::Text(text) => {
+            out.write_all(&[0])?;
+            write_bytes(out, text)?;
+        }
+        Key::Digest(digest) => {
+            out.write_all(&[1])?;
+            out.write_all(&digest.to_le_bytes())?;
+        }
+    }
+    write_entry(out, entry)
+}
+
+fn read_record(input: &mut impl BufRead) -> io::Result<Option<(Key, Entry)>> {
+    if input.fill_buf()?.is_empty() {
+        return Ok(None);
+    }
+    let mut tag = [0; 1];
+    input.read_exact(&mut tag)?;
+    let key = if tag[0] == 0 {
+        Key::Text(read_bytes(input)?)
+    } else {
+        let mut digest = [0; 16];
+        input.read_exact(&mut digest)?;
+        Key::Digest(u128::from_le_bytes(digest))
+    };
+    Ok(Some((key, read_entry(input)?)))
+}
+
+fn read_result(input: &mut impl BufRead) -> io::Result<Option<Entry>> {
+    if input.fill_buf()?.is_empty() {
+        return Ok(None);
+    }
+    read_entry(input).map(Some)
+}
+
+fn write_entry(out: &mut impl Write, entry: &Entry) -> io::Result<()> {
+    out.write_all(&entry.seq.to_le_bytes())?;
+    out.write_all(&entry.count.to_le_bytes())?;
+    match &entry.line {
+        Some(line) => {
+            out.write_all(&[1])?;
+            write_bytes(out, line)
+        }
+        None => out.write_all(&[0]),
+    }
+}
+
+fn read_entry(input: &mut impl Read) -> io::Result<Entry> {
+    let seq = read_u64(input)?;
+    let count = read_u64(input)?;
+    let mut flag = [0; 1];
+    input.read_exact(&mut flag)?;
+    let line = if flag[0] == 1 { Some(read_bytes(input)?) } else { None };
+    Ok(Entry { seq, count, line })
+}
+
+fn write_bytes(out: &mut impl Write, bytes: &[u8]) -> io::Result<()> {
+    out.write_all(&(bytes.len() as u64).to_le_bytes())?;
+    out.write_all(bytes)
+}
+
+fn read_bytes(input: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut bytes = vec![0; read_u64(input)? as usize];
+    input.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_u64(input: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
@@ -1,17 +1,86 @@
+use crate::global::{Dedup, HashBits, Keep};
+use crate::records::{Column, Input};
+use crate::sketch::{HyperLogLog, SpaceSaving};
 use clap::{App, Arg};
 use std::{
+    borrow::Cow,
+    collections::BinaryHeap,
     error::Error,
-    fs::File,
-    io::{self, BufRead, BufReader, Write},
+    fs::{self, File},
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    path::Path,
 };
+use unicode_normalization::UnicodeNormalization;
+
+mod global;
+mod records;
+mod sketch;
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
+// Options that have no meaning for the fixed-memory estimates
+const SKETCH_CONFLICTS: [&str; 10] = [
+    "repeated",
+    "unique",
+    "all_repeated",
+    "global",
+    "hash",
+    "max_memory",
+    "top",
+    "bottom",
+    "min_count",
+    "keep",
+];
+
+// How `--all-repeated` separates the groups it prints
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Delimit {
+    None,
+    Prepend,
+    Separate,
+}
+
+// Which lines a frequency report keeps
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Rank {
+    Top(usize),
+    Bottom(usize),
+    All,
+}
+
+// The Unicode normalization form used for comparing lines
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Normalize {
+    Nfc,
+    Nfkc,
+}
+
 #[derive(Debug)]
 pub struct Config {
-    in_file: String,
+    in_files: Vec<String>,
     out_file: Option<String>,
     count: bool,
+    repeated: bool,
+    unique: bool,
+    all_repeated: Option<Delimit>,
+    skip_fields: usize,
+    skip_chars: usize,
+    check_chars: Option<usize>,
+    ignore_case: bool,
+    collapse_whitespace: bool,
+    trim: bool,
+    normalize: Option<Normalize>,
+    global: bool,
+    hash: Option<HashBits>,
+    max_memory: Option<usize>,
+    rank: Option<Rank>,
+    min_count: u64,
+    estimate_distinct: Option<u32>,
+    heavy_hitters: Option<usize>,
+    key: Option<Vec<Column>>,
+    header: bool,
+    delimiter: u8,
+    keep: Keep,
 }
 
 pub fn get_args() -> MyResult<Config> {
@@ -20,14 +89,17 @@ pub fn get_args() -> MyResult<Config> {
         .author("Anup Jadhav <anup.jadhav@gmail.com>")
         .about("Rust uniq")
         .arg(
-            Arg::with_name("in_file")
-                .value_name("IN_FILE")
-                .help("Input file")
+            Arg::with_name("files")
+                .value_name("FILE")
+                .help("Input file(s), or IN_FILE OUT_FILE without --output")
+                .multiple(true)
                 .default_value("-"),
         )
         .arg(
-            Arg::with_name("out_file")
-                .value_name("OUT_FILE")
+            Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .value_name("FILE")
                 .help("Output file"),
         )
         .arg(
@@ -37,15 +109,533 @@ pub fn get_args() -> MyResult<Config> {
                 .help("Show counts")
                 .takes_value(false),
         )
+        .arg(
+            Arg::with_name("repeated")
+                .short("d")
+                .long("repeated")
+                .help("Only print one copy of each repeated line")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("unique")
+                .short("u")
+                .long("unique")
+                .help("Only print lines that are not repeated")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("all_repeated")
+                .short("D")
+                .long("all-repeated")
+                .value_name("METHOD")
+                .help("Print every copy of each repeated line")
+                .possible_values(&["none", "prepend", "separate"])
+                .min_values(0)
+                .require_equals(true)
+                .conflicts_with_all(&["count", "unique"]),
+        )
+        .arg(
+            Arg::with_name("skip_fields")
+                .short("f")
+                .long("skip-fields")
+                .value_name("N")
+                .help("Avoid comparing the first N fields"),
+        )
+        .arg(
+            Arg::with_name("skip_chars")
+                .short("s")
+                .long("skip-chars")
+                .value_name("N")
+                .help("Avoid comparing the first N characters"),
+        )
+        .arg(
+            Arg::with_name("check_chars")
+                .short("w")
+                .long("check-chars")
+                .value_name("N")
+                .help("Compare no more than N characters"),
+        )
+        .arg(
+            Arg::with_name("ignore_case")
+                .short("i")
+                .long("ignore-case")
+                .help("Ignore differences in case when comparing")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("collapse_whitespace")
+                .long("collapse-whitespace")
+                .help("Compare runs of whitespace as a single space")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("trim")
+                .long("trim")
+                .help("Ignore leading and trailing whitespace when comparing")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("normalize")
+                .long("normalize")
+                .value_name("FORM")
+                .help("Compare lines in this Unicode normalization form")
+                .possible_values(&["nfc", "nfkc"]),
+        )
+        .arg(
+            Arg::with_name("global")
+                .long("global")
+                .help("Remove repeated lines anywhere in the input")
+                .takes_value(false)
+                .conflicts_with("all_repeated"),
+        )
+        .arg(
+            Arg::with_name("hash")
+                .long("hash")
+                .value_name("BITS")
+                .help("Remember lines by a hash of this size")
+                .possible_values(&["64", "128"]),
+        )
+        .arg(
+            Arg::with_name("max_memory")
+                .long("max-memory")
+                .value_name("SIZE")
+                .help("Spill to disk beyond SIZE bytes (K, M, G)"),
+        )
+        .arg(
+            Arg::with_name("top")
+                .long("top")
+                .value_name("K")
+                .help("Print the K most frequent lines with counts")
+                .conflicts_with_all(&[
+                    "bottom",
+                    "repeated",
+                    "unique",
+                    "all_repeated",
+                ]),
+        )
+        .arg(
+            Arg::with_name("bottom")
+                .long("bottom")
+                .value_name("K")
+                .help("Print the K least frequent lines with counts")
+                .conflicts_with_all(&["repeated", "unique", "all_repeated"]),
+        )
+        .arg(
+            Arg::with_name("min_count")
+                .long("min-count")
+                .value_name("N")
+                .help("Print lines seen at least N times, most frequent first")
+                .conflicts_with_all(&["repeated", "unique", "all_repeated"]),
+        )
+        .arg(
+            Arg::with_name("estimate_distinct")
+                .long("estimate-distinct")
+                .help("Estimate the number of distinct lines in fixed memory")
+                .takes_value(false)
+                .conflicts_with_all(&SKETCH_CONFLICTS),
+        )
+        .arg(
+            Arg::with_name("precision")
+                .long("precision")
+                .value_name("P")
+                .help(
+                    "Use 2^P registers for --estimate-distinct, with an \
+                     error of 1.04/sqrt(2^P) [4-18, default: 14]",
+                )
+                .requires("estimate_distinct"),
+        )
+        .arg(
+            Arg::with_name("heavy_hitters")
+                .long("heavy-hitters")
+                .value_name("K")
+                .help(
+                    "Estimate the K most frequent lines with 10K counters; \
+                     counts may be over by the amount in parentheses",
+                )
+                .conflicts_with_all(&SKETCH_CONFLICTS)
+                .conflicts_with("estimate_distinct"),
+        )
+        .arg(
+            Arg::with_name("key")
+                .long("key")
+                .value_name("COLUMNS")
+                .help(
+                    "Read CSV rows and compare them by these columns, \
+                     numbered from 1 or named in the header",
+                )
+                .conflicts_with_all(&[
+                    "skip_fields",
+                    "skip_chars",
+                    "check_chars",
+                ]),
+        )
+        .arg(
+            Arg::with_name("header")
+                .long("header")
+                .help(
+                    "Treat the first row as a header that names the \
+                     --key columns and is always printed",
+                )
+                .requires("key"),
+        )
+        .arg(
+            Arg::with_name("delimiter")
+                .long("delimiter")
+                .value_name("DELIMITER")
+                .help("Field delimiter for --key [default: ,]")
+                .requires("key"),
+        )
+        .arg(
+            Arg::with_name("keep")
+                .long("keep")
+                .value_name("WHICH")
+                .help(
+                    "Print the first or last line of each group \
+                     [default: first]",
+                )
+                .possible_values(&["first", "last"])
+                .conflicts_with("all_repeated"),
+        )
         .get_matches();
 
+    let all_repeated = if matches.is_present("all_repeated") {
+        match matches.value_of("all_repeated") {
+            Some("prepend") => Some(Delimit::Prepend),
+            Some("separate") => Some(Delimit::Separate),
+            _ => Some(Delimit::None),
+        }
+    } else {
+        None
+    };
+
+    let skip_fields =
+        matches.value_of("skip_fields").map(parse_num).transpose().map_err(
+            |e| format!("invalid number of fields to skip -- {}", e),
+        )?;
+
+    let skip_chars =
+        matches.value_of("skip_chars").map(parse_num).transpose().map_err(
+            |e| format!("invalid number of characters to skip -- {}", e),
+        )?;
+
+    let check_chars =
+        matches.value_of("check_chars").map(parse_num).transpose().map_err(
+            |e| format!("invalid number of characters to compare -- {}", e),
+        )?;
+
+    let max_memory = matches
+        .value_of("max_memory")
+        .map(parse_size)
+        .transpose()
+        .map_err(|e| format!("invalid memory size -- {}", e))?;
+
+    let top = matches
+        .value_of("top")
+        .map(parse_num)
+        .transpose()
+        .map_err(|e| format!("invalid number of lines -- {}", e))?;
+
+    let bottom = matches
+        .value_of("bottom")
+        .map(parse_num)
+        .transpose()
+        .map_err(|e| format!("invalid number of lines -- {}", e))?;
+
+    let min_count = matches
+        .value_of("min_count")
+        .map(parse_num)
+        .transpose()
+        .map_err(|e| format!("invalid count -- {}", e))?;
+
+    let precision = matches
+        .value_of("precision")
+        .map(parse_precision)
+        .transpose()
+        .map_err(|e| format!("invalid precision -- {}", e))?;
+
+    let heavy_hitters = matches
+        .value_of("heavy_hitters")
+        .map(parse_num)
+        .transpose()
+        .map_err(|e| format!("invalid number of lines -- {}", e))?;
+
+    let key = matches.value_of("key").map(parse_columns).transpose()?;
+
+    let delimiter = matches.value_of("delimiter").unwrap_or(",");
+    if delimiter.len() != 1 {
+        return Err(format!(
+            "--delimiter \"{}\" must be a single byte",
+            delimiter
+        )
+        .into());
+    }
+
+    let rank = match (top, bottom, min_count) {
+        (Some(k), _, _) => Some(Rank::Top(k)),
+        (_, Some(k), _) => Some(Rank::Bottom(k)),
+        (_, _, Some(_)) => Some(Rank::All),
+        _ => None,
+    };
+
+    let global = matches.is_present("global");
+    for name in ["hash", "max_memory"] {
+        if matches.is_present(name) && !global && rank.is_none() {
+            return Err(format!(
+                "--{} needs --global, --top, --bottom or --min-count",
+                name.replace('_', "-")
+            )
+            .into());
+        }
+    }
+
+    // Without --output, a second file is the output as with uniq
+    let mut in_files = matches.values_of_lossy("files").unwrap();
+    let out_file = match matches.value_of_lossy("output") {
+        Some(out) => Some(out.to_string()),
+        None if in_files.len() > 2 => {
+            return Err(format!(
+                "extra operand '{}' (use -o to read several files)",
+                in_files[2]
+            )
+            .into())
+        }
+        None if in_files.len() == 2 => in_files.pop(),
+        None => None,
+    };
+
     Ok(Config {
-        in_file: matches.value_of_lossy("in_file").map(Into::into).unwrap(),
-        out_file: matches.value_of_lossy("out_file").map(|v| v.to_string()),
-        count: matches.is_present("count"),
+        in_files,
+        out_file: out_file.filter(|out| out != "-"),
+        count: matches.is_present("count") || rank.is_some(),
+        repeated: matches.is_present("repeated"),
+        unique: matches.is_present("unique"),
+        all_repeated,
+        skip_fields: skip_fields.unwrap_or(0),
+        skip_chars: skip_chars.unwrap_or(0),
+        check_chars,
+        ignore_case: matches.is_present("ignore_case"),
+        collapse_whitespace: matches.is_present("collapse_whitespace"),
+        trim: matches.is_present("trim"),
+        normalize: match matches.value_of("normalize") {
+            Some("nfc") => Some(Normalize::Nfc),
+            Some("nfkc") => Some(Normalize::Nfkc),
+            _ => None,
+        },
+        global,
+        hash: match matches.value_of("hash") {
+            Some("64") => Some(HashBits::Bits64),
+            Some("128") => Some(HashBits::Bits128),
+            _ => None,
+        },
+        max_memory,
+        rank,
+        min_count: min_count.unwrap_or(0) as u64,
+        estimate_distinct: matches
+            .is_present("estimate_distinct")
+            .then(|| precision.unwrap_or(HyperLogLog::DEFAULT_PRECISION)),
+        heavy_hitters,
+        key,
+        header: matches.is_present("header"),
+        delimiter: delimiter.as_bytes()[0],
+        keep: match matches.value_of("keep") {
+            Some("last") => Keep::Last,
+            _ => Keep::First,
+        },
+    })
+}
+
+fn parse_num(val: &str) -> MyResult<usize> {
+    val.parse().map_err(|_| val.into())
+}
+
+fn parse_precision(val: &str) -> MyResult<u32> {
+    let range = HyperLogLog::MIN_PRECISION..=HyperLogLog::MAX_PRECISION;
+    match val.parse() {
+        Ok(p) if range.contains(&p) => Ok(p),
+        _ => Err(val.into()),
+    }
+}
+
+// Comma-separated columns, where anything but a number is a name
+fn parse_columns(val: &str) -> MyResult<Vec<Column>> {
+    val.split(',')
+        .map(|column| match column.parse::<usize>() {
+            Ok(0) => Err(format!("invalid column -- {}", column).into()),
+            Ok(i) => Ok(Column::Index(i)),
+            Err(_) if column.is_empty() => {
+                Err(format!("invalid column -- {}", val).into())
+            }
+            Err(_) => Ok(Column::Name(column.to_string())),
+        })
+        .collect()
+}
+
+// A number of bytes with an optional K, M or G suffix
+fn parse_size(val: &str) -> MyResult<usize> {
+    let (num, scale) = match val.char_indices().last() {
+        Some((i, 'K' | 'k')) => (&val[..i], 1 << 10),
+        Some((i, 'M' | 'm')) => (&val[..i], 1 << 20),
+        Some((i, 'G' | 'g')) => (&val[..i], 1 << 30),
+        _ => (val, 1),
+    };
+    num.parse::<usize>()
+        .ok()
+        .and_then(|num| num.checked_mul(scale))
+        .ok_or_else(|| val.into())
+}
+
+// The bytes a line is compared by
+fn compare_key<'a>(line: &'a [u8], config: &Config) -> Cow<'a, [u8]> {
+    fold_key(line, config, select_key)
+}
+
+// The bytes a CSV field is compared by
+fn field_key<'a>(field: &'a [u8], config: &Config) -> Cow<'a, [u8]> {
+    fold_key(field, config, |field, config| {
+        if config.trim {
+            trim_start(trim_end(field))
+        } else {
+            field
+        }
     })
 }
 
+// Normalizing and collapsing whitespace happen before `select`
+// so that `-f`, `-s` and `-w` count the same characters for
+// equivalent lines, and case is folded last. These only change
+// the valid UTF-8 parts of a line; other bytes compare as they are.
+fn fold_key<'a>(
+    text: &'a [u8],
+    config: &Config,
+    select: for<'b> fn(&'b [u8], &Config) -> &'b [u8],
+) -> Cow<'a, [u8]> {
+    let text = match config.normalize {
+        Some(Normalize::Nfc) => {
+            Cow::Owned(map_valid(text, |text| text.nfc().collect()))
+        }
+        Some(Normalize::Nfkc) => {
+            Cow::Owned(map_valid(text, |text| text.nfkc().collect()))
+        }
+        None => Cow::Borrowed(text),
+    };
+
+    let text = if config.collapse_whitespace {
+        Cow::Owned(map_valid(&text, collapse_whitespace))
+    } else {
+        text
+    };
+
+    let key = match text {
+        Cow::Borrowed(text) => Cow::Borrowed(select(text, config)),
+        Cow::Owned(text) => Cow::Owned(select(&text, config).to_vec()),
+    };
+
+    if config.ignore_case {
+        Cow::Owned(map_valid(&key, str::to_lowercase))
+    } else {
+        key
+    }
+}
+
+// Rewrite each run of valid UTF-8 in `bytes` with `f`
+fn map_valid(bytes: &[u8], f: impl Fn(&str) -> String) -> Vec<u8> {
+    let mut mapped = Vec::with_capacity(bytes.len());
+    for chunk in bytes.utf8_chunks() {
+        mapped.extend_from_slice(f(chunk.valid()).as_bytes());
+        mapped.extend_from_slice(chunk.invalid());
+    }
+    mapped
+}
+
+fn collapse_whitespace(line: &str) -> String {
+    let mut collapsed = String::with_capacity(line.len());
+    let mut in_space = false;
+    for c in line.chars() {
+        if c.is_whitespace() {
+            if !in_space {
+                collapsed.push(' ');
+            }
+            in_space = true;
+        } else {
+            collapsed.push(c);
+            in_space = false;
+        }
+    }
+    collapsed
+}
+
+// The part of a line selected by `--trim`, `-f`, `-s` and `-w`:
+// a field is a run of blanks followed by nonblanks, and fields
+// are skipped before characters
+fn select_key<'a>(line: &'a [u8], config: &Config) -> &'a [u8] {
+    let is_blank = |b: &u8| *b == b' ' || *b == b'\t';
+    let mut key = trim_end(line);
+    if config.trim {
+        key = trim_start(key);
+    }
+    for _ in 0..config.skip_fields {
+        let start = key.iter().position(|b| !is_blank(b)).unwrap_or(key.len());
+        key = &key[start..];
+        let end = key.iter().position(is_blank).unwrap_or(key.len());
+        key = &key[end..];
+    }
+
+    key = match char_offset(key, config.skip_chars) {
+        Some(i) => &key[i..],
+        None => &[],
+    };
+
+    match config.check_chars.and_then(|n| char_offset(key, n)) {
+        Some(i) => &key[..i],
+        None => key,
+    }
+}
+
+// The offset of character `n`, counting each byte that is not
+// part of a valid UTF-8 character as one character
+fn char_offset(bytes: &[u8], n: usize) -> Option<usize> {
+    let mut offset = 0;
+    let mut count = 0;
+    for chunk in bytes.utf8_chunks() {
+        for (i, _) in chunk.valid().char_indices() {
+            if count == n {
+                return Some(offset + i);
+            }
+            count += 1;
+        }
+        offset += chunk.valid().len();
+        for i in 0..chunk.invalid().len() {
+            if count == n {
+                return Some(offset + i);
+            }
+            count += 1;
+        }
+        offset += chunk.invalid().len();
+    }
+    None
+}
+
+// Whitespace is only trimmed up to the first invalid byte
+fn trim_start(bytes: &[u8]) -> &[u8] {
+    match bytes.utf8_chunks().next() {
+        Some(chunk) => {
+            let valid = chunk.valid();
+            &bytes[valid.len() - valid.trim_start().len()..]
+        }
+        None => bytes,
+    }
+}
+
+fn trim_end(bytes: &[u8]) -> &[u8] {
+    match bytes.utf8_chunks().last() {
+        Some(chunk) if chunk.invalid().is_empty() => {
+            let valid = chunk.valid();
+            &bytes[..bytes.len() - valid.len() + valid.trim_end().len()]
+        }
+        _ => bytes,
+    }
+}
+
 fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
     match filename {
         "-" => Ok(Box::new(BufReader::new(io::stdin()))),
@@ -53,44 +643,285 @@ fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
     }
 }
 
-pub fn run(config: Config) -> MyResult<()> {
-    let mut file = open(&config.in_file)
-        .map_err(|e| format!("{}: {}", config.in_file, e))?;
-    // `out_file` is a boxed value that
-    // implements the `std::io::Write` trait
-    let mut out_file: Box<dyn Write> = match &config.out_file {
-        Some(out_name) => Box::new(File::create(out_name)?),
-        _ => Box::new(io::stdout()),
-    };
+// Reads the input files one after another as a single stream,
+// opening each only when the one before it is finished
+struct Concat {
+    files: std::vec::IntoIter<String>,
+    current: Box<dyn BufRead>,
+}
+
+impl Concat {
+    fn new(first: Box<dyn BufRead>, rest: Vec<String>) -> Self {
+        Concat { files: rest.into_iter(), current: first }
+    }
+}
+
+impl Read for Concat {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
 
-    let mut print = |count: u64, text: &str| -> MyResult<()> {
-        if count > 0 {
-            if config.count {
-                write!(out_file, "{:>4} {}", count, text)?;
-            } else {
-                write!(out_file, "{}", text)?;
+impl BufRead for Concat {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        while self.current.fill_buf()?.is_empty() {
+            match self.files.next() {
+                Some(filename) => {
+                    self.current = open(&filename).map_err(|e| {
+                        io::Error::other(format!("{}: {}", filename, e))
+                    })?;
+                }
+                None => break,
             }
         }
-        Ok(())
+        self.current.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.current.consume(amt)
+    }
+}
+
+#[cfg(unix)]
+fn is_same_file(a: &str, b: &str) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn is_same_file(a: &str, b: &str) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+// Write to a temporary file beside `out_name` and rename it into
+// place once `write` succeeds, so that a failed run leaves any
+// existing file untouched. Devices and pipes are written directly.
+fn write_atomically(
+    out_name: &str,
+    write: impl FnOnce(&mut dyn Write) -> MyResult<()>,
+) -> MyResult<()> {
+    let existing = fs::metadata(out_name).ok();
+    if existing.as_ref().is_some_and(|meta| !meta.is_file()) {
+        let mut out_file = BufWriter::new(File::create(out_name)?);
+        write(&mut out_file)?;
+        return Ok(out_file.flush()?);
+    }
+
+    // Replace the target of a symlink rather than the link itself
+    let path = match existing {
+        Some(_) => fs::canonicalize(out_name)?,
+        None => Path::new(out_name).to_path_buf(),
+    };
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
     };
 
-    let mut line = String::new();
-    let mut previous = String::new();
-    let mut count: u64 = 0;
-    loop {
-        let bytes = file.read_line(&mut line)?;
-        if bytes == 0 {
-            break;
+    let mut builder = tempfile::Builder::new();
+    builder.prefix(".uniqr");
+    #[cfg(unix)]
+    {
+        // Created like `File::create`, subject to the umask
+        use std::os::unix::fs::PermissionsExt;
+        builder.permissions(fs::Permissions::from_mode(0o666));
+    }
+    let temp = builder
+        .tempfile_in(dir)
+        .map_err(|e| format!("{}: {}", out_name, e))?;
+    if let Some(meta) = existing {
+        temp.as_file().set_permissions(meta.permissions())?;
+    }
+
+    let mut out_file = BufWriter::new(temp);
+    write(&mut out_file)?;
+    let temp = out_file.into_inner().map_err(|e| e.into_error())?;
+    temp.persist(&path).map_err(|e| format!("{}: {}", out_name, e.error))?;
+    Ok(())
+}
+
+pub fn run(config: Config) -> MyResult<()> {
+    if let Some(out_name) = &config.out_file {
+        if let Some(in_file) = config
+            .in_files
+            .iter()
+            .find(|in_file| *in_file != "-" && is_same_file(in_file, out_name))
+        {
+            return Err(
+                format!("{}: input file is output file", in_file).into()
+            );
+        }
+    }
+
+    let first = &config.in_files[0];
+    let file = open(first).map_err(|e| format!("{}: {}", first, e))?;
+    let file = Box::new(Concat::new(file, config.in_files[1..].to_vec()));
+    let (input, header) =
+        Input::new(file, &config).map_err(|e| format!("{}: {}", first, e))?;
+    match &config.out_file {
+        Some(out_name) => write_atomically(out_name, |out_file| {
+            uniq(&config, input, header, out_file)
+        }),
+        None => uniq(&config, input, header, &mut io::stdout()),
+    }
+}
+
+fn uniq(
+    config: &Config,
+    mut input: Input,
+    header: Option<Vec<u8>>,
+    out_file: &mut dyn Write,
+) -> MyResult<()> {
+    let mut line = vec![];
+    if let Some(precision) = config.estimate_distinct {
+        let mut hll = HyperLogLog::new(precision);
+        while let Some((key, _)) = input.read(&mut line, config)? {
+            hll.insert(&key);
+        }
+        writeln!(out_file, "{}", hll.estimate())?;
+        return Ok(());
+    }
+
+    if let Some(k) = config.heavy_hitters {
+        let mut counters = SpaceSaving::new(k.saturating_mul(10));
+        while let Some((key, text)) = input.read(&mut line, config)? {
+            counters.insert(&key, text);
         }
+        if let Some(header) = header {
+            out_file.write_all(&header)?;
+        }
+        for (count, error, text) in counters.top(k) {
+            write!(out_file, "{:>4} ({}) ", count, error)?;
+            out_file.write_all(text)?;
+        }
+        return Ok(());
+    }
+
+    if let Some(header) = header {
+        out_file.write_all(&header)?;
+    }
+
+    // `repeats` holds the later copies of a group for `--all-repeated`
+    let mut groups_printed = 0;
+    let mut print =
+        |count: u64, text: &[u8], repeats: &[Vec<u8>]| -> MyResult<()> {
+            if count == 0 {
+                return Ok(());
+            }
+
+            if let Some(delimit) = config.all_repeated {
+                if count > 1 {
+                    if delimit == Delimit::Prepend
+                        || (delimit == Delimit::Separate && groups_printed > 0)
+                    {
+                        writeln!(out_file)?;
+                    }
+                    out_file.write_all(text)?;
+                    for repeat in repeats {
+                        out_file.write_all(repeat)?;
+                    }
+                    groups_printed += 1;
+                }
+            } else if (count > 1 && !config.unique)
+                || (count == 1 && !config.repeated)
+            {
+                if config.count {
+                    write!(out_file, "{:>4} ", count)?;
+                }
+                out_file.write_all(text)?;
+            }
+            Ok(())
+        };
+
+    if let Some(rank) = config.rank {
+        let mut dedup =
+            Dedup::new(config.hash, config.max_memory, true, config.keep);
+        while let Some((key, text)) = input.read(&mut line, config)? {
+            dedup.insert(&key, text)?;
+        }
+        for (count, text) in rank_lines(dedup, rank, config.min_count)? {
+            print(count, &text, &[])?;
+        }
+        return Ok(());
+    }
 
-        if line.trim_end() != previous.trim_end() {
-            print(count, &previous)?;
-            previous = line.clone();
+    if config.global {
+        let counting = config.count || config.repeated || config.unique;
+        let mut dedup =
+            Dedup::new(config.hash, config.max_memory, counting, config.keep);
+        while let Some((key, text)) = input.read(&mut line, config)? {
+            if dedup.insert(&key, text)? {
+                print(1, text, &[])?;
+            }
+        }
+        return dedup.finish(|count, text| print(count, text, &[]));
+    }
+
+    let mut previous = vec![];
+    let mut previous_key = vec![];
+    let mut repeats = vec![];
+    let mut count: u64 = 0;
+    while let Some((key, text)) = input.read(&mut line, config)? {
+        if count == 0 || key != previous_key {
+            print(count, &previous, &repeats)?;
+            previous_key = key.into_owned();
+            previous = text.to_vec();
+            repeats.clear();
             count = 0;
+        } else if config.all_repeated.is_some() {
+            repeats.push(text.to_vec());
+        } else if config.keep == Keep::Last {
+            previous = text.to_vec();
         }
         count += 1;
-        line.clear();
     }
-    print(count, &previous)?;
+    print(count, &previous, &repeats)?;
     Ok(())
 }
+
+// Order the counted lines by frequency, breaking ties by first
+// appearance. Only K lines are held for `--top` and `--bottom`.
+fn rank_lines(
+    dedup: Dedup,
+    rank: Rank,
+    min_count: u64,
+) -> MyResult<Vec<(u64, Vec<u8>)>> {
+    let limit = match rank {
+        Rank::Top(k) | Rank::Bottom(k) => Some(k),
+        Rank::All => None,
+    };
+
+    // The heap keeps the smallest sort keys, so most frequent
+    // first means sorting by negated counts
+    let mut heap = BinaryHeap::new();
+    let mut seq: u64 = 0;
+    dedup.finish(|count, text| {
+        seq += 1;
+        if count >= min_count {
+            let sort_count = match rank {
+                Rank::Bottom(_) => i128::from(count),
+                _ => -i128::from(count),
+            };
+            heap.push((sort_count, seq, text.to_vec()));
+            if limit.is_some_and(|k| heap.len() > k) {
+                heap.pop();
+            }
+        }
+        Ok(())
+    })?;
+
+    Ok(heap
+        .into_sorted_vec()
+        .into_iter()
+        .map(|(sort_count, _, text)| (sort_count.unsigned_abs() as u64, text))
+        .collect())
+}
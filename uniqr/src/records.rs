@@ -0,0 +1,209 @@
+use crate::{compare_key, field_key, Config, MyResult};
+use csv::{ByteRecord, Reader, ReaderBuilder};
+use std::{
+    borrow::Cow,
+    io::{self, BufRead, Read},
+};
+
+// A column given to `--key`, numbered from 1 or named in the header
+#[derive(Debug, Clone, PartialEq)]
+pub enum Column {
+    Index(usize),
+    Name(String),
+}
+
+// The key a record is compared by, and the record itself
+type Record<'a> = (Cow<'a, [u8]>, &'a [u8]);
+
+// The records being deduplicated: lines, or CSV rows compared
+// by the columns given to `--key`
+pub enum Input {
+    Lines(Box<dyn BufRead>),
+    Csv(Box<CsvInput>),
+}
+
+pub struct CsvInput {
+    reader: Reader<Recorder>,
+    record: ByteRecord,
+    columns: Vec<usize>,
+    delimiter: u8,
+}
+
+// Keeps a copy of the bytes the CSV reader takes from the file, so
+// that rows can be written as they were read rather than as parsed
+struct Recorder {
+    file: Box<dyn BufRead>,
+    bytes: Vec<u8>,
+    // The file offset of `bytes[0]`, and of the end of the last row
+    start: u64,
+    done: u64,
+}
+
+impl Read for Recorder {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // Rows already read are no longer needed
+        self.bytes.drain(..(self.done - self.start) as usize);
+        self.start = self.done;
+        let n = self.file.read(buf)?;
+        self.bytes.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+}
+
+impl CsvInput {
+    // Read the next row into `text` as the bytes it was written with.
+    // Rows may span lines when a quoted field holds a line break, and
+    // the blank lines the reader skips are left out.
+    fn read_row(&mut self, text: &mut Vec<u8>) -> MyResult<bool> {
+        if !self.reader.read_byte_record(&mut self.record)? {
+            return Ok(false);
+        }
+        let first = self.record.position().map_or(0, |pos| pos.byte());
+        let last = self.reader.position().byte();
+        let recorder = self.reader.get_mut();
+        let offset = |pos: u64| (pos - recorder.start) as usize;
+        let row = &recorder.bytes[offset(first)..offset(last)];
+        recorder.done = last;
+
+        // A row's position is where the reader started looking for it,
+        // ahead of any blank lines it skipped. It ends a row at a CR,
+        // leaving the LF of a CR LF pair to be skipped the same way.
+        let blank = row.iter().take_while(|&&b| matches!(b, b'\r' | b'\n'));
+        text.extend_from_slice(&row[blank.count()..]);
+        if text.last() == Some(&b'\r') {
+            let next = match recorder.bytes.get(offset(last)) {
+                Some(&b) => Some(b),
+                None => recorder.file.fill_buf()?.first().copied(),
+            };
+            if next == Some(b'\n') {
+                text.push(b'\n');
+            }
+        }
+        Ok(true)
+    }
+
+    fn field(&self, i: usize) -> &[u8] {
+        self.record.get(i).unwrap_or_default()
+    }
+}
+
+impl Input {
+    // With `--header` the first row is read here so that columns can
+    // be named, and is returned to be printed ahead of the other rows
+    pub fn new(
+        file: Box<dyn BufRead>,
+        config: &Config,
+    ) -> MyResult<(Self, Option<Vec<u8>>)> {
+        let key = match &config.key {
+            Some(key) => key,
+            None => return Ok((Input::Lines(file), None)),
+        };
+
+        let recorder = Recorder { file, bytes: vec![], start: 0, done: 0 };
+        let reader = ReaderBuilder::new()
+            .delimiter(config.delimiter)
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(recorder);
+        let mut csv = CsvInput {
+            reader,
+            record: ByteRecord::new(),
+            columns: vec![],
+            delimiter: config.delimiter,
+        };
+
+        let mut header = vec![];
+        let header =
+            (config.header && csv.read_row(&mut header)?).then_some(header);
+        let names = match header {
+            Some(_) => csv.record.clone(),
+            None => ByteRecord::new(),
+        };
+
+        csv.columns = key
+            .iter()
+            .map(|column| match column {
+                Column::Index(i) => Ok(i - 1),
+                Column::Name(name) if header.is_none() => {
+                    Err(format!("column name needs --header -- {}", name))
+                }
+                Column::Name(name) => names
+                    .iter()
+                    .position(|field| field == name.as_bytes())
+                    .ok_or_else(|| format!("unknown column -- {}", name)),
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok((Input::Csv(Box::new(csv)), header))
+    }
+
+    // Read the next record into `text` and return the key it is
+    // compared by along with the text
+    pub fn read<'a>(
+        &mut self,
+        text: &'a mut Vec<u8>,
+        config: &Config,
+    ) -> MyResult<Option<Record<'a>>> {
+        text.clear();
+        match self {
+            Input::Lines(file) => {
+                if file.read_until(b'\n', text)? == 0 {
+                    return Ok(None);
+                }
+                let text: &'a [u8] = text;
+                Ok(Some((compare_key(text, config), text)))
+            }
+            Input::Csv(csv) => {
+                if !csv.read_row(text)? {
+                    return Ok(None);
+                }
+                let text: &'a [u8] = text;
+
+                // Quoting the folded fields keeps the key unambiguous
+                let mut key = vec![];
+                let fields = csv
+                    .columns
+                    .iter()
+                    .map(|&i| field_key(csv.field(i), config));
+                write_fields(&mut key, fields, csv.delimiter);
+                Ok(Some((Cow::Owned(key), text)))
+            }
+        }
+    }
+}
+
+// Append a CSV row, quoting only the fields that need it
+fn write_fields<S: AsRef<[u8]>>(
+    out: &mut Vec<u8>,
+    fields: impl Iterator<Item = S>,
+    delimiter: u8,
+) {
+    let start = out.len();
+    for (i, field) in fields.enumerate() {
+        let field = field.as_ref();
+        if i > 0 {
+            out.push(delimiter);
+        }
+        if field
+            .iter()
+            .any(|&b| matches!(b, b'"' | b'\n' | b'\r') || b == delimiter)
+        {
+            out.push(b'"');
+            for &b in field {
+                if b == b'"' {
+                    out.push(b'"');
+                }
+                out.push(b);
+            }
+            out.push(b'"');
+        } else {
+            out.extend_from_slice(field);
+        }
+    }
+
+    // An empty row would be read back as no row at all
+    if out.len() == start {
+        out.extend_from_slice(b"\"\"");
+    }
+    out.push(b'\n');
+}
@@ -0,0 +1,131 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeSet, HashMap},
+};
+use xxhash_rust::xxh3::{xxh3_128, xxh3_64};
+
+// Estimates the number of distinct keys in 2^precision bytes.
+// The relative standard error is 1.04 / sqrt(2^precision),
+// about 0.8% for the default precision of 14.
+#[derive(Debug)]
+pub struct HyperLogLog {
+    precision: u32,
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    pub const MIN_PRECISION: u32 = 4;
+    pub const MAX_PRECISION: u32 = 18;
+    pub const DEFAULT_PRECISION: u32 = 14;
+
+    pub fn new(precision: u32) -> Self {
+        HyperLogLog { precision, registers: vec![0; 1 << precision] }
+    }
+
+    // The first `precision` bits of the hash pick a register, which
+    // keeps the longest run of leading zeros seen in the rest
+    pub fn insert(&mut self, key: &[u8]) {
+        let hash = xxh3_64(key);
+        let index = (hash >> (64 - self.precision)) as usize;
+        let rest = hash << self.precision;
+        let rank = (rest.leading_zeros() + 1).min(65 - self.precision) as u8;
+        if rank > self.registers[index] {
+            self.registers[index] = rank;
+        }
+    }
+
+    pub fn estimate(&self) -> u64 {
+        let m = self.registers.len() as f64;
+        let alpha = match self.registers.len() {
+            16 => 0.673,
+            32 => 0.697,
+            64 => 0.709,
+            _ => 0.7213 / (1.0 + 1.079 / m),
+        };
+        let sum: f64 =
+            self.registers.iter().map(|&r| 2f64.powi(-i32::from(r))).sum();
+        let raw = alpha * m * m / sum;
+
+        // Linear counting is more accurate for small cardinalities
+        let zeros = self.registers.iter().filter(|&&r| r == 0).count();
+        let estimate = if raw <= 2.5 * m && zeros > 0 {
+            m * (m / zeros as f64).ln()
+        } else {
+            raw
+        };
+        estimate.round() as u64
+    }
+}
+
+// The Space-Saving algorithm: a fixed number of counters, where an
+// unseen key takes over the smallest counter. A key's count is too
+// high by at most its `error`, which never exceeds N / capacity.
+#[derive(Debug)]
+pub struct SpaceSaving {
+    capacity: usize,
+    counters: Vec<Counter>,
+    index: HashMap<u128, usize>,
+    by_count: BTreeSet<(u64, usize)>,
+}
+
+#[derive(Debug)]
+struct Counter {
+    key: u128,
+    count: u64,
+    error: u64,
+    line: Vec<u8>,
+}
+
+impl SpaceSaving {
+    pub fn new(capacity: usize) -> Self {
+        SpaceSaving {
+            capacity: capacity.max(1),
+            counters: vec![],
+            index: HashMap::new(),
+            by_count: BTreeSet::new(),
+        }
+    }
+
+    pub fn insert(&mut self, key: &[u8], line: &[u8]) {
+        let key = xxh3_128(key);
+        if let Some(&i) = self.index.get(&key) {
+            self.increment(i);
+        } else if self.counters.len() < self.capacity {
+            let i = self.counters.len();
+            let line = line.to_vec();
+            self.counters.push(Counter { key, count: 1, error: 0, line });
+            self.index.insert(key, i);
+            self.by_count.insert((1, i));
+        } else if let Some((min, i)) = self.by_count.pop_first() {
+            let counter = &mut self.counters[i];
+            self.index.remove(&counter.key);
+            self.index.insert(key, i);
+            *counter =
+                Counter { key, count: min, error: min, line: line.to_vec() };
+            self.by_count.insert((min, i));
+            self.increment(i);
+        }
+    }
+
+    fn increment(&mut self, i: usize) {
+        let counter = &mut self.counters[i];
+        self.by_count.remove(&(counter.count, i));
+        counter.count += 1;
+        self.by_count.insert((counter.count, i));
+    }
+
+    // The `k` largest counts with their errors, ties broken by the
+    // order in which the counters were first used
+    pub fn top(&self, k: usize) -> Vec<(u64, u64, &[u8])> {
+        let mut ranked: Vec<_> = self.by_count.iter().copied().collect();
+        ranked.sort_unstable_by_key(|&(count, i)| (Reverse(count), i));
+        ranked
+            .into_iter()
+            .take(k)
+            .map(|(count, i)| {
+                let counter = &self.counters[i];
+                (count, counter.error, counter.line.as_slice())
+            })
+            .collect()
+    }
+}
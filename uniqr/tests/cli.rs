@@ -1,7 +1,12 @@
+#![allow(clippy::needless_borrows_for_generic_args)]
+
 use assert_cmd::Command;
 use predicates::prelude::*;
-use rand::{distributions::Alphanumeric, Rng};
-use std::fs;
+use rand::{
+    distributions::Alphanumeric, rngs::StdRng, seq::SliceRandom, Rng,
+    SeedableRng,
+};
+use std::{collections::HashMap, fs};
 use tempfile::NamedTempFile;
 
 type TestResult = Result<(), Box<dyn std::error::Error>>;
@@ -80,6 +85,42 @@ const T6: Test = Test {
     out_count: "tests/expected/t6.txt.c.out",
 };
 
+const FIELDS: Test = Test {
+    input: "tests/inputs/fields.txt",
+    out: "tests/expected/fields.txt.out",
+    out_count: "tests/expected/fields.txt.c.out",
+};
+
+const EVENTS: Test = Test {
+    input: "tests/inputs/events.txt",
+    out: "tests/expected/events.txt.out",
+    out_count: "tests/expected/events.txt.c.out",
+};
+
+const GLOBAL: Test = Test {
+    input: "tests/inputs/global.txt",
+    out: "tests/expected/global.txt.out",
+    out_count: "tests/expected/global.txt.c.out",
+};
+
+const BINARY: Test = Test {
+    input: "tests/inputs/binary.txt",
+    out: "tests/expected/binary.txt.out",
+    out_count: "tests/expected/binary.txt.c.out",
+};
+
+const ORDERS: Test = Test {
+    input: "tests/inputs/orders.csv",
+    out: "tests/expected/orders.csv.out",
+    out_count: "tests/expected/orders.csv.c.out",
+};
+
+const PEOPLE: Test = Test {
+    input: "tests/inputs/people.tsv",
+    out: "tests/expected/people.tsv.out",
+    out_count: "tests/expected/people.tsv.c.out",
+};
+
 // --------------------------------------------------
 fn gen_bad_file() -> String {
     loop {
@@ -109,499 +150,1716 @@ fn dies_bad_file() -> TestResult {
 }
 
 // --------------------------------------------------
-// HELPER FUNCTIONS
-fn run(test: &Test) -> TestResult {
-    let expected = fs::read_to_string(test.out)?;
+#[test]
+fn dies_all_repeated_count() -> TestResult {
     Command::cargo_bin(PRG)?
-        .arg(test.input)
+        .args(["-D", "-c", T1.input])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_all_repeated() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--all-repeated=foo", T1.input])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("isn't a valid value"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_skip_fields() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-f", "foo", FIELDS.input])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "invalid number of fields to skip -- foo",
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_skip_chars() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-s", "x1", FIELDS.input])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "invalid number of characters to skip -- x1",
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_check_chars() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-w", "1.5", FIELDS.input])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "invalid number of characters to compare -- 1.5",
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_normalize() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--normalize=nfd", EVENTS.input])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("isn't a valid value"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_max_memory() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--global", "--max-memory", "12X", GLOBAL.input])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid memory size -- 12X"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_hash_without_global() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--hash", "64", GLOBAL.input])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--global"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn global_spills_to_disk() -> TestResult {
+    // Enough random lines to spill several times under a 4K limit
+    let mut rng = rand::thread_rng();
+    let input: String = (0..5000)
+        .map(|_| format!("line {}\n", rng.gen_range(0..1500)))
+        .collect();
+
+    for flags in [&["--global"][..], &["--global", "-c"], &["--global", "-u"]]
+    {
+        let expected = Command::cargo_bin(PRG)?
+            .args(flags)
+            .write_stdin(input.clone())
+            .output()?
+            .stdout;
+
+        for hash in [&[][..], &["--hash", "64"], &["--hash", "128"]] {
+            Command::cargo_bin(PRG)?
+                .args(flags)
+                .args(hash)
+                .args(["--max-memory", "4K"])
+                .write_stdin(input.clone())
+                .assert()
+                .success()
+                .stdout(expected.clone());
+        }
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn keep_last_spills_to_disk() -> TestResult {
+    // Rows are `key,row number`, so the row kept shows which one won
+    let mut rng = rand::thread_rng();
+    let keys: Vec<u32> = (0..5000).map(|_| rng.gen_range(0..1500)).collect();
+    let input: String = keys
+        .iter()
+        .enumerate()
+        .map(|(i, k)| format!("{},{}\n", k, i))
+        .collect();
+
+    let mut last = HashMap::new();
+    for (i, key) in keys.iter().enumerate() {
+        last.insert(key, i);
+    }
+    let mut kept: Vec<_> = last.into_iter().collect();
+    kept.sort_by_key(|&(_, i)| i);
+    let expected: String =
+        kept.iter().map(|(k, i)| format!("{},{}\n", k, i)).collect();
+
+    for memory in [&[][..], &["--max-memory", "4K"]] {
+        Command::cargo_bin(PRG)?
+            .args(["--key", "1", "--global", "--keep", "last"])
+            .args(memory)
+            .write_stdin(input.clone())
+            .assert()
+            .success()
+            .stdout(expected.clone());
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_extra_operand() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args([ONE.input, TWO.input, THREE.input])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(format!(
+            "extra operand '{}'",
+            THREE.input
+        )));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_input_is_output() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let input = dir.path().join("input.txt");
+    fs::copy(THREE.input, &input)?;
+    let link = dir.path().join("link.txt");
+    fs::hard_link(&input, &link)?;
+    let expected = fs::read_to_string(THREE.input)?;
+
+    for out in [&input, &link] {
+        Command::cargo_bin(PRG)?
+            .arg(&input)
+            .arg(out)
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("input file is output file"));
+
+        Command::cargo_bin(PRG)?
+            .arg(ONE.input)
+            .arg(&input)
+            .arg("-o")
+            .arg(out)
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("input file is output file"));
+    }
+    assert_eq!(fs::read_to_string(&input)?, expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn failed_run_keeps_output() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let out = dir.path().join("out.txt");
+    fs::write(&out, "old\n")?;
+
+    let bad = gen_bad_file();
+    Command::cargo_bin(PRG)?
+        .args([THREE.input, &bad, "-o"])
+        .arg(&out)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(bad));
+
+    // Nothing is left behind but the untouched output
+    assert_eq!(fs::read_to_string(&out)?, "old\n");
+    assert_eq!(fs::read_dir(dir.path())?.count(), 1);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn multiple_inputs() -> TestResult {
+    // The files are read as one stream, so groups span files
+    let expected = "a\nb\nc\n";
+    let dir = tempfile::tempdir()?;
+    let first = dir.path().join("first.txt");
+    let second = dir.path().join("second.txt");
+    fs::write(&first, "a\na\nb\n")?;
+    fs::write(&second, "b\nc\n")?;
+
+    Command::cargo_bin(PRG)?
+        .arg(&first)
+        .arg(&second)
+        .args(["-o", "-"])
         .assert()
         .success()
         .stdout(expected);
+
+    let out = dir.path().join("out.txt");
+    Command::cargo_bin(PRG)?
+        .arg(&first)
+        .arg("-")
+        .arg("--output")
+        .arg(&out)
+        .write_stdin("b\nc\n")
+        .assert()
+        .success()
+        .stdout("");
+    assert_eq!(fs::read_to_string(&out)?, expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn csv_key_with_invalid_utf8() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--key", "name", "--header", "-i"])
+        .write_stdin(&b"name,id\nCaf\xe9,1\ncaf\xe9,2\ncaf\xc9,3\n"[..])
+        .assert()
+        .success()
+        .stdout(&b"name,id\nCaf\xe9,1\ncaf\xc9,3\n"[..]);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn csv_key_keeps_original_bytes() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--key", "1", "--global"])
+        .write_stdin("\"a\",\"b\"\r\n\r\na,c\r\n\"x\n\",\"y\"\"\"\r\nz,\"\"")
+        .assert()
+        .success()
+        .stdout("\"a\",\"b\"\r\n\"x\n\",\"y\"\"\"\r\nz,\"\"");
+    Command::cargo_bin(PRG)?
+        .args(["--key", "1"])
+        .write_stdin("a,1\ra,2\rb,3\n\r")
+        .assert()
+        .success()
+        .stdout("a,1\rb,3\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn csv_key_keeps_long_rows() -> TestResult {
+    // Quoted line breaks and blank lines well past the reader's buffer
+    let long =
+        format!("a,\"{}\r\n{}\"\r\n", "x".repeat(10_000), "y".repeat(10_000));
+    let input = format!("{}\r\n\r\n{}b,1\r\n\n{}", long, long, long);
+    Command::cargo_bin(PRG)?
+        .args(["--key", "1", "--global"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(format!("{}b,1\r\n", long));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn csv_header_with_numeric_key() -> TestResult {
+    let input = "id,v\n1,a\nid,b\n1,c\n";
+    Command::cargo_bin(PRG)?
+        .args(["--key", "1", "--header", "--global", "-c"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout("id,v\n   2 1,a\n   1 id,b\n");
+    Command::cargo_bin(PRG)?
+        .args(["--key", "1", "--global", "-c"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout("   2 id,v\n   2 1,a\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_top() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--top", "ten", THREE.input])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid number of lines -- ten"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_min_count() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--min-count", "-", THREE.input])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid count -- -"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_top_and_bottom() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--top", "1", "--bottom", "1", THREE.input])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
     Ok(())
 }
 
 // --------------------------------------------------
-fn run_count(test: &Test) -> TestResult {
-    let expected = fs::read_to_string(test.out_count)?;
-    Command::cargo_bin(PRG)?
-        .args(&[test.input, "-c"])
-        .assert()
-        .success()
-        .stdout(expected);
-    Ok(())
+#[test]
+fn top_spills_to_disk() -> TestResult {
+    let mut rng = rand::thread_rng();
+    let input: String = (0..5000)
+        .map(|_| format!("line {}\n", rng.gen_range(0..1500)))
+        .collect();
+
+    for flags in
+        [&["--top", "20"][..], &["--bottom", "20"], &["--min-count", "5"]]
+    {
+        let expected = Command::cargo_bin(PRG)?
+            .args(flags)
+            .write_stdin(input.clone())
+            .output()?
+            .stdout;
+
+        Command::cargo_bin(PRG)?
+            .args(flags)
+            .args(["--max-memory", "4K", "--hash", "128"])
+            .write_stdin(input.clone())
+            .assert()
+            .success()
+            .stdout(expected);
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_delimiter_without_key() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--delimiter", ";", ORDERS.input])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--key <COLUMNS>"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_precision() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--estimate-distinct", "--precision", "19", THREE.input])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid precision -- 19"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_key_column() -> TestResult {
+    for (key, expected) in [
+        ("0", "invalid column -- 0"),
+        ("1,,2", "invalid column -- 1,,2"),
+        ("customer", "unknown column -- customer"),
+    ] {
+        Command::cargo_bin(PRG)?
+            .args(["--key", key, "--header", ORDERS.input])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(expected));
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_key_column_names_file() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let out = dir.path().join("out.txt");
+    let expected = format!("{}: unknown column -- customer", ORDERS.input);
+    Command::cargo_bin(PRG)?
+        .args(["--key", "customer", "--header", ORDERS.input, THREE.input])
+        .arg("-o")
+        .arg(&out)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(expected));
+
+    // The output is not created when the inputs cannot be read
+    assert_eq!(fs::read_dir(dir.path())?.count(), 0);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_key_name_without_header() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--key", "date", ORDERS.input])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "column name needs --header -- date",
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_delimiter() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--key", "1", "--delimiter", ";;", ORDERS.input])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            r#"--delimiter ";;" must be a single byte"#,
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_key_and_skip_fields() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--key", "1", "-f", "1", ORDERS.input])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_heavy_hitters_and_top() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--heavy-hitters", "2", "--top", "2", THREE.input])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn estimate_distinct_within_tolerance() -> TestResult {
+    // The standard error is 1.04/sqrt(2^P), so allow three of them
+    let mut rng = StdRng::seed_from_u64(34);
+    for distinct in [10, 1000, 60000] {
+        let mut lines: Vec<String> = (0..distinct * 3)
+            .map(|i| format!("user-{}\n", i % distinct))
+            .collect();
+        lines.shuffle(&mut rng);
+        let input = lines.concat();
+
+        for precision in [10, 14] {
+            let output = Command::cargo_bin(PRG)?
+                .args(["--estimate-distinct", "--precision"])
+                .arg(precision.to_string())
+                .write_stdin(input.clone())
+                .output()?;
+            assert!(output.status.success());
+
+            let estimate: f64 =
+                String::from_utf8(output.stdout)?.trim().parse()?;
+            let error = (estimate - distinct as f64).abs() / distinct as f64;
+            let tolerance = 3.0 * 1.04 / 2f64.powi(precision).sqrt();
+            assert!(
+                error <= tolerance,
+                "{} distinct at precision {}: estimated {}",
+                distinct,
+                precision,
+                estimate
+            );
+        }
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn estimate_distinct_uses_compare_key() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--estimate-distinct", "-i", "--normalize=nfkc", EVENTS.input])
+        .assert()
+        .success()
+        .stdout("5\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn heavy_hitters_within_error_bounds() -> TestResult {
+    // Line `item-i` appears 20000/i times among 20000 unique lines
+    let mut rng = StdRng::seed_from_u64(34);
+    let mut lines: Vec<String> = (1..=50)
+        .flat_map(|i| vec![format!("item-{}\n", i); 20000 / i])
+        .chain((0..20000).map(|i| format!("noise-{}\n", i)))
+        .collect();
+    lines.shuffle(&mut rng);
+    let total = lines.len() as u64;
+
+    let k = 5;
+    let output = Command::cargo_bin(PRG)?
+        .args(["--heavy-hitters", &k.to_string()])
+        .write_stdin(lines.concat())
+        .output()?;
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout)?;
+    let reported: Vec<(u64, u64, &str)> = stdout
+        .lines()
+        .map(|line| {
+            let mut parts = line.split_whitespace();
+            let count = parts.next().unwrap().parse().unwrap();
+            let error = parts.next().unwrap().trim_matches(&['(', ')'][..]);
+            (count, error.parse().unwrap(), parts.next().unwrap())
+        })
+        .collect();
+    assert_eq!(reported.len(), k);
+
+    // Counts are over by no more than their error, which is at most N/10K
+    for (count, error, item) in &reported {
+        let i: u64 = item.trim_start_matches("item-").parse()?;
+        let actual = 20000 / i;
+        assert!(actual <= *count && *count - error <= actual, "{}", item);
+        assert!(*error <= total / (10 * k as u64));
+    }
+
+    // The clear winners are found in order
+    let items: Vec<_> = reported.iter().map(|(_, _, item)| *item).collect();
+    assert_eq!(items[..3], ["item-1", "item-2", "item-3"]);
+    Ok(())
+}
+
+// --------------------------------------------------
+// HELPER FUNCTIONS
+fn run(test: &Test) -> TestResult {
+    let expected = fs::read(test.out)?;
+    Command::cargo_bin(PRG)?
+        .arg(test.input)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+fn run_count(test: &Test) -> TestResult {
+    let expected = fs::read(test.out_count)?;
+    Command::cargo_bin(PRG)?
+        .args(&[test.input, "-c"])
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+fn run_stdin(test: &Test) -> TestResult {
+    let input = fs::read(test.input)?;
+    let expected = fs::read(test.out)?;
+    Command::cargo_bin(PRG)?
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+fn run_stdin_count(test: &Test) -> TestResult {
+    let input = fs::read(test.input)?;
+    let expected = fs::read(test.out_count)?;
+    Command::cargo_bin(PRG)?
+        .arg("--count")
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+fn run_outfile(test: &Test) -> TestResult {
+    let expected = fs::read(test.out)?;
+    let outfile = NamedTempFile::new()?;
+    let outpath = &outfile.path().to_str().unwrap();
+    Command::cargo_bin(PRG)?
+        .args(&[test.input, outpath])
+        .assert()
+        .success()
+        .stdout("");
+
+    let contents = fs::read(&outpath)?;
+    assert_eq!(&expected, &contents);
+
+    Ok(())
+}
+
+// --------------------------------------------------
+fn run_outfile_count(test: &Test) -> TestResult {
+    let outfile = NamedTempFile::new()?;
+    let outpath = &outfile.path().to_str().unwrap();
+
+    Command::cargo_bin(PRG)?
+        .args(&[test.input, outpath, "--count"])
+        .assert()
+        .success()
+        .stdout("");
+
+    let expected = fs::read(test.out_count)?;
+    let contents = fs::read(&outpath)?;
+    assert_eq!(&expected, &contents);
+
+    Ok(())
+}
+
+// --------------------------------------------------
+fn run_stdin_outfile_count(test: &Test) -> TestResult {
+    let input = fs::read(test.input)?;
+    let outfile = NamedTempFile::new()?;
+    let outpath = &outfile.path().to_str().unwrap();
+
+    Command::cargo_bin(PRG)?
+        .args(&["-", outpath, "-c"])
+        .write_stdin(input)
+        .assert()
+        .stdout("");
+
+    let expected = fs::read(test.out_count)?;
+    let contents = fs::read(&outpath)?;
+    assert_eq!(&expected, &contents);
+
+    Ok(())
+}
+
+// --------------------------------------------------
+fn run_flags(test: &Test, flags: &[&str], suffix: &str) -> TestResult {
+    let expected_file = test.out.replace(".out", &format!(".{}.out", suffix));
+    let expected = fs::read(expected_file)?;
+    Command::cargo_bin(PRG)?
+        .arg(test.input)
+        .args(flags)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn empty() -> TestResult {
+    run(&EMPTY)
+}
+
+#[test]
+fn empty_count() -> TestResult {
+    run_count(&EMPTY)
+}
+
+#[test]
+fn empty_stdin() -> TestResult {
+    run_stdin(&EMPTY)
+}
+
+#[test]
+fn empty_stdin_count() -> TestResult {
+    run_stdin_count(&EMPTY)
+}
+
+#[test]
+fn empty_outfile() -> TestResult {
+    run_outfile(&EMPTY)
+}
+
+#[test]
+fn empty_outfile_count() -> TestResult {
+    run_outfile_count(&EMPTY)
+}
+
+#[test]
+fn empty_stdin_outfile_count() -> TestResult {
+    run_stdin_outfile_count(&EMPTY)
+}
+
+// --------------------------------------------------
+#[test]
+fn one() -> TestResult {
+    run(&ONE)
+}
+
+#[test]
+fn one_count() -> TestResult {
+    run_count(&ONE)
+}
+
+#[test]
+fn one_stdin() -> TestResult {
+    run_stdin(&ONE)
+}
+
+#[test]
+fn one_stdin_count() -> TestResult {
+    run_stdin_count(&ONE)
+}
+
+#[test]
+fn one_outfile() -> TestResult {
+    run_outfile(&ONE)
+}
+
+#[test]
+fn one_outfile_count() -> TestResult {
+    run_outfile_count(&ONE)
+}
+
+#[test]
+fn one_stdin_outfile_count() -> TestResult {
+    run_stdin_outfile_count(&ONE)
+}
+
+// --------------------------------------------------
+#[test]
+fn two() -> TestResult {
+    run(&TWO)
+}
+
+#[test]
+fn two_count() -> TestResult {
+    run_count(&TWO)
+}
+
+#[test]
+fn two_stdin() -> TestResult {
+    run_stdin(&TWO)
+}
+
+#[test]
+fn two_stdin_count() -> TestResult {
+    run_stdin_count(&TWO)
+}
+
+#[test]
+fn two_outfile() -> TestResult {
+    run_outfile(&TWO)
+}
+
+#[test]
+fn two_outfile_count() -> TestResult {
+    run_outfile_count(&TWO)
+}
+
+#[test]
+fn two_stdin_outfile_count() -> TestResult {
+    run_stdin_outfile_count(&TWO)
+}
+
+// --------------------------------------------------
+#[test]
+fn three() -> TestResult {
+    run(&THREE)
+}
+
+#[test]
+fn three_count() -> TestResult {
+    run_count(&THREE)
+}
+
+#[test]
+fn three_stdin() -> TestResult {
+    run_stdin(&THREE)
+}
+
+#[test]
+fn three_stdin_count() -> TestResult {
+    run_stdin_count(&THREE)
+}
+
+#[test]
+fn three_outfile() -> TestResult {
+    run_outfile(&THREE)
+}
+
+#[test]
+fn three_outfile_count() -> TestResult {
+    run_outfile_count(&THREE)
+}
+
+#[test]
+fn three_stdin_outfile_count() -> TestResult {
+    run_stdin_outfile_count(&THREE)
+}
+
+// --------------------------------------------------
+#[test]
+fn skip() -> TestResult {
+    run(&SKIP)
+}
+
+#[test]
+fn skip_count() -> TestResult {
+    run_count(&SKIP)
+}
+
+#[test]
+fn skip_stdin() -> TestResult {
+    run_stdin(&SKIP)
+}
+
+#[test]
+fn skip_stdin_count() -> TestResult {
+    run_stdin_count(&SKIP)
+}
+
+#[test]
+fn skip_outfile() -> TestResult {
+    run_outfile(&SKIP)
+}
+
+#[test]
+fn skip_outfile_count() -> TestResult {
+    run_outfile_count(&SKIP)
+}
+
+#[test]
+fn skip_stdin_outfile_count() -> TestResult {
+    run_stdin_outfile_count(&SKIP)
+}
+
+// --------------------------------------------------
+#[test]
+fn t1() -> TestResult {
+    run(&T1)
+}
+
+#[test]
+fn t1_count() -> TestResult {
+    run_count(&T1)
+}
+
+#[test]
+fn t1_stdin() -> TestResult {
+    run_stdin(&T1)
+}
+
+#[test]
+fn t1_stdin_count() -> TestResult {
+    run_stdin_count(&T1)
+}
+
+#[test]
+fn t1_outfile() -> TestResult {
+    run_outfile(&T1)
+}
+
+#[test]
+fn t1_outfile_count() -> TestResult {
+    run_outfile_count(&T1)
+}
+
+#[test]
+fn t1_stdin_outfile_count() -> TestResult {
+    run_stdin_outfile_count(&T1)
+}
+
+// --------------------------------------------------
+#[test]
+fn t2() -> TestResult {
+    run(&T2)
+}
+
+#[test]
+fn t2_count() -> TestResult {
+    run_count(&T2)
+}
+
+#[test]
+fn t2_stdin() -> TestResult {
+    run_stdin(&T2)
+}
+
+#[test]
+fn t2_stdin_count() -> TestResult {
+    run_stdin_count(&T2)
+}
+
+#[test]
+fn t2_outfile() -> TestResult {
+    run_outfile(&T2)
+}
+
+#[test]
+fn t2_outfile_count() -> TestResult {
+    run_outfile_count(&T2)
+}
+
+#[test]
+fn t2_stdin_outfile_count() -> TestResult {
+    run_stdin_outfile_count(&T2)
+}
+
+// --------------------------------------------------
+#[test]
+fn t3() -> TestResult {
+    run(&T3)
+}
+
+#[test]
+fn t3_count() -> TestResult {
+    run_count(&T3)
+}
+
+#[test]
+fn t3_stdin() -> TestResult {
+    run_stdin(&T3)
+}
+
+#[test]
+fn t3_stdin_count() -> TestResult {
+    run_stdin_count(&T3)
+}
+
+#[test]
+fn t3_outfile() -> TestResult {
+    run_outfile(&T3)
+}
+
+#[test]
+fn t3_outfile_count() -> TestResult {
+    run_outfile_count(&T3)
+}
+
+#[test]
+fn t3_stdin_outfile_count() -> TestResult {
+    run_stdin_outfile_count(&T3)
+}
+
+// --------------------------------------------------
+#[test]
+fn t4() -> TestResult {
+    run(&T4)
+}
+
+#[test]
+fn t4_count() -> TestResult {
+    run_count(&T4)
+}
+
+#[test]
+fn t4_stdin() -> TestResult {
+    run_stdin(&T4)
+}
+
+#[test]
+fn t4_stdin_count() -> TestResult {
+    run_stdin_count(&T4)
+}
+
+#[test]
+fn t4_outfile() -> TestResult {
+    run_outfile(&T4)
+}
+
+#[test]
+fn t4_outfile_count() -> TestResult {
+    run_outfile_count(&T4)
+}
+
+#[test]
+fn t4_stdin_outfile_count() -> TestResult {
+    run_stdin_outfile_count(&T4)
+}
+
+// --------------------------------------------------
+#[test]
+fn t5() -> TestResult {
+    run(&T5)
+}
+
+#[test]
+fn t5_count() -> TestResult {
+    run_count(&T5)
+}
+
+#[test]
+fn t5_stdin() -> TestResult {
+    run_stdin(&T5)
+}
+
+#[test]
+fn t5_stdin_count() -> TestResult {
+    run_stdin_count(&T5)
+}
+
+#[test]
+fn t5_outfile() -> TestResult {
+    run_outfile(&T5)
+}
+
+#[test]
+fn t5_outfile_count() -> TestResult {
+    run_outfile_count(&T5)
+}
+
+#[test]
+fn t5_stdin_outfile_count() -> TestResult {
+    run_stdin_outfile_count(&T5)
+}
+
+// --------------------------------------------------
+#[test]
+fn t6() -> TestResult {
+    run(&T6)
+}
+
+#[test]
+fn t6_count() -> TestResult {
+    run_count(&T6)
+}
+
+#[test]
+fn t6_stdin() -> TestResult {
+    run_stdin(&T6)
+}
+
+#[test]
+fn t6_stdin_count() -> TestResult {
+    run_stdin_count(&T6)
+}
+
+#[test]
+fn t6_outfile() -> TestResult {
+    run_outfile(&T6)
+}
+
+#[test]
+fn t6_outfile_count() -> TestResult {
+    run_outfile_count(&T6)
+}
+
+#[test]
+fn t6_stdin_outfile_count() -> TestResult {
+    run_stdin_outfile_count(&T6)
+}
+
+// --------------------------------------------------
+#[test]
+fn t1_repeated() -> TestResult {
+    run_flags(&T1, &["-d"], "d")
+}
+
+#[test]
+fn t1_repeated_count() -> TestResult {
+    run_flags(&T1, &["-d", "-c"], "dc")
+}
+
+#[test]
+fn t1_unique() -> TestResult {
+    run_flags(&T1, &["-u"], "u")
+}
+
+#[test]
+fn t1_unique_count() -> TestResult {
+    run_flags(&T1, &["-u", "--count"], "uc")
+}
+
+#[test]
+fn t1_repeated_unique() -> TestResult {
+    run_flags(&T1, &["-d", "-u"], "du")
+}
+
+#[test]
+fn t1_all_repeated() -> TestResult {
+    run_flags(&T1, &["-D"], "D")
+}
+
+#[test]
+fn t1_all_repeated_prepend() -> TestResult {
+    run_flags(&T1, &["--all-repeated=prepend"], "Dprepend")
+}
+
+#[test]
+fn t1_all_repeated_separate() -> TestResult {
+    run_flags(&T1, &["--all-repeated=separate"], "Dseparate")
+}
+
+// --------------------------------------------------
+#[test]
+fn t2_repeated() -> TestResult {
+    run_flags(&T2, &["-d"], "d")
+}
+
+#[test]
+fn t2_repeated_count() -> TestResult {
+    run_flags(&T2, &["-d", "-c"], "dc")
+}
+
+#[test]
+fn t2_unique() -> TestResult {
+    run_flags(&T2, &["-u"], "u")
+}
+
+#[test]
+fn t2_unique_count() -> TestResult {
+    run_flags(&T2, &["-u", "--count"], "uc")
+}
+
+#[test]
+fn t2_repeated_unique() -> TestResult {
+    run_flags(&T2, &["-d", "-u"], "du")
+}
+
+#[test]
+fn t2_all_repeated() -> TestResult {
+    run_flags(&T2, &["-D"], "D")
+}
+
+#[test]
+fn t2_all_repeated_prepend() -> TestResult {
+    run_flags(&T2, &["--all-repeated=prepend"], "Dprepend")
+}
+
+#[test]
+fn t2_all_repeated_separate() -> TestResult {
+    run_flags(&T2, &["--all-repeated=separate"], "Dseparate")
+}
+
+// --------------------------------------------------
+#[test]
+fn t3_repeated() -> TestResult {
+    run_flags(&T3, &["-d"], "d")
 }
 
-// --------------------------------------------------
-fn run_stdin(test: &Test) -> TestResult {
-    let input = fs::read_to_string(test.input)?;
-    let expected = fs::read_to_string(test.out)?;
-    Command::cargo_bin(PRG)?
-        .write_stdin(input)
-        .assert()
-        .success()
-        .stdout(expected);
-    Ok(())
+#[test]
+fn t3_repeated_count() -> TestResult {
+    run_flags(&T3, &["-d", "-c"], "dc")
 }
 
-// --------------------------------------------------
-fn run_stdin_count(test: &Test) -> TestResult {
-    let input = fs::read_to_string(test.input)?;
-    let expected = fs::read_to_string(test.out_count)?;
-    Command::cargo_bin(PRG)?
-        .arg("--count")
-        .write_stdin(input)
-        .assert()
-        .success()
-        .stdout(expected);
-    Ok(())
+#[test]
+fn t3_unique() -> TestResult {
+    run_flags(&T3, &["-u"], "u")
 }
 
-// --------------------------------------------------
-fn run_outfile(test: &Test) -> TestResult {
-    let expected = fs::read_to_string(test.out)?;
-    let outfile = NamedTempFile::new()?;
-    let outpath = &outfile.path().to_str().unwrap();
-    Command::cargo_bin(PRG)?
-        .args(&[test.input, outpath])
-        .assert()
-        .success()
-        .stdout("");
+#[test]
+fn t3_unique_count() -> TestResult {
+    run_flags(&T3, &["-u", "--count"], "uc")
+}
 
-    let contents = fs::read_to_string(&outpath)?;
-    assert_eq!(&expected, &contents);
+#[test]
+fn t3_repeated_unique() -> TestResult {
+    run_flags(&T3, &["-d", "-u"], "du")
+}
 
-    Ok(())
+#[test]
+fn t3_all_repeated() -> TestResult {
+    run_flags(&T3, &["-D"], "D")
+}
+
+#[test]
+fn t3_all_repeated_prepend() -> TestResult {
+    run_flags(&T3, &["--all-repeated=prepend"], "Dprepend")
+}
+
+#[test]
+fn t3_all_repeated_separate() -> TestResult {
+    run_flags(&T3, &["--all-repeated=separate"], "Dseparate")
 }
 
 // --------------------------------------------------
-fn run_outfile_count(test: &Test) -> TestResult {
-    let outfile = NamedTempFile::new()?;
-    let outpath = &outfile.path().to_str().unwrap();
+#[test]
+fn t4_repeated() -> TestResult {
+    run_flags(&T4, &["-d"], "d")
+}
 
-    Command::cargo_bin(PRG)?
-        .args(&[test.input, outpath, "--count"])
-        .assert()
-        .success()
-        .stdout("");
+#[test]
+fn t4_repeated_count() -> TestResult {
+    run_flags(&T4, &["-d", "-c"], "dc")
+}
 
-    let expected = fs::read_to_string(test.out_count)?;
-    let contents = fs::read_to_string(&outpath)?;
-    assert_eq!(&expected, &contents);
+#[test]
+fn t4_unique() -> TestResult {
+    run_flags(&T4, &["-u"], "u")
+}
 
-    Ok(())
+#[test]
+fn t4_unique_count() -> TestResult {
+    run_flags(&T4, &["-u", "--count"], "uc")
 }
 
-// --------------------------------------------------
-fn run_stdin_outfile_count(test: &Test) -> TestResult {
-    let input = fs::read_to_string(test.input)?;
-    let outfile = NamedTempFile::new()?;
-    let outpath = &outfile.path().to_str().unwrap();
+#[test]
+fn t4_repeated_unique() -> TestResult {
+    run_flags(&T4, &["-d", "-u"], "du")
+}
 
-    Command::cargo_bin(PRG)?
-        .args(&["-", outpath, "-c"])
-        .write_stdin(input)
-        .assert()
-        .stdout("");
+#[test]
+fn t4_all_repeated() -> TestResult {
+    run_flags(&T4, &["-D"], "D")
+}
 
-    let expected = fs::read_to_string(test.out_count)?;
-    let contents = fs::read_to_string(&outpath)?;
-    assert_eq!(&expected, &contents);
+#[test]
+fn t4_all_repeated_prepend() -> TestResult {
+    run_flags(&T4, &["--all-repeated=prepend"], "Dprepend")
+}
 
-    Ok(())
+#[test]
+fn t4_all_repeated_separate() -> TestResult {
+    run_flags(&T4, &["--all-repeated=separate"], "Dseparate")
 }
 
 // --------------------------------------------------
 #[test]
-fn empty() -> TestResult {
-    run(&EMPTY)
+fn t5_repeated() -> TestResult {
+    run_flags(&T5, &["-d"], "d")
 }
 
 #[test]
-fn empty_count() -> TestResult {
-    run_count(&EMPTY)
+fn t5_repeated_count() -> TestResult {
+    run_flags(&T5, &["-d", "-c"], "dc")
 }
 
 #[test]
-fn empty_stdin() -> TestResult {
-    run_stdin(&EMPTY)
+fn t5_unique() -> TestResult {
+    run_flags(&T5, &["-u"], "u")
 }
 
 #[test]
-fn empty_stdin_count() -> TestResult {
-    run_stdin_count(&EMPTY)
+fn t5_unique_count() -> TestResult {
+    run_flags(&T5, &["-u", "--count"], "uc")
 }
 
 #[test]
-fn empty_outfile() -> TestResult {
-    run_outfile(&EMPTY)
+fn t5_repeated_unique() -> TestResult {
+    run_flags(&T5, &["-d", "-u"], "du")
 }
 
 #[test]
-fn empty_outfile_count() -> TestResult {
-    run_outfile_count(&EMPTY)
+fn t5_all_repeated() -> TestResult {
+    run_flags(&T5, &["-D"], "D")
 }
 
 #[test]
-fn empty_stdin_outfile_count() -> TestResult {
-    run_stdin_outfile_count(&EMPTY)
+fn t5_all_repeated_prepend() -> TestResult {
+    run_flags(&T5, &["--all-repeated=prepend"], "Dprepend")
 }
 
-// --------------------------------------------------
 #[test]
-fn one() -> TestResult {
-    run(&ONE)
+fn t5_all_repeated_separate() -> TestResult {
+    run_flags(&T5, &["--all-repeated=separate"], "Dseparate")
 }
 
+// --------------------------------------------------
 #[test]
-fn one_count() -> TestResult {
-    run_count(&ONE)
+fn t6_repeated() -> TestResult {
+    run_flags(&T6, &["-d"], "d")
 }
 
 #[test]
-fn one_stdin() -> TestResult {
-    run_stdin(&ONE)
+fn t6_repeated_count() -> TestResult {
+    run_flags(&T6, &["-d", "-c"], "dc")
 }
 
 #[test]
-fn one_stdin_count() -> TestResult {
-    run_stdin_count(&ONE)
+fn t6_unique() -> TestResult {
+    run_flags(&T6, &["-u"], "u")
 }
 
 #[test]
-fn one_outfile() -> TestResult {
-    run_outfile(&ONE)
+fn t6_unique_count() -> TestResult {
+    run_flags(&T6, &["-u", "--count"], "uc")
 }
 
 #[test]
-fn one_outfile_count() -> TestResult {
-    run_outfile_count(&ONE)
+fn t6_repeated_unique() -> TestResult {
+    run_flags(&T6, &["-d", "-u"], "du")
 }
 
 #[test]
-fn one_stdin_outfile_count() -> TestResult {
-    run_stdin_outfile_count(&ONE)
+fn t6_all_repeated() -> TestResult {
+    run_flags(&T6, &["-D"], "D")
 }
 
-// --------------------------------------------------
 #[test]
-fn two() -> TestResult {
-    run(&TWO)
+fn t6_all_repeated_prepend() -> TestResult {
+    run_flags(&T6, &["--all-repeated=prepend"], "Dprepend")
 }
 
 #[test]
-fn two_count() -> TestResult {
-    run_count(&TWO)
+fn t6_all_repeated_separate() -> TestResult {
+    run_flags(&T6, &["--all-repeated=separate"], "Dseparate")
 }
 
+// --------------------------------------------------
 #[test]
-fn two_stdin() -> TestResult {
-    run_stdin(&TWO)
+fn three_all_repeated() -> TestResult {
+    run_flags(&THREE, &["-D"], "D")
 }
 
 #[test]
-fn two_stdin_count() -> TestResult {
-    run_stdin_count(&TWO)
+fn three_all_repeated_prepend() -> TestResult {
+    run_flags(&THREE, &["--all-repeated=prepend"], "Dprepend")
 }
 
 #[test]
-fn two_outfile() -> TestResult {
-    run_outfile(&TWO)
+fn three_all_repeated_separate() -> TestResult {
+    run_flags(&THREE, &["--all-repeated=separate"], "Dseparate")
 }
 
+// --------------------------------------------------
 #[test]
-fn two_outfile_count() -> TestResult {
-    run_outfile_count(&TWO)
+fn fields() -> TestResult {
+    run(&FIELDS)
 }
 
 #[test]
-fn two_stdin_outfile_count() -> TestResult {
-    run_stdin_outfile_count(&TWO)
+fn fields_count() -> TestResult {
+    run_count(&FIELDS)
 }
 
-// --------------------------------------------------
 #[test]
-fn three() -> TestResult {
-    run(&THREE)
+fn fields_skip_fields() -> TestResult {
+    run_flags(&FIELDS, &["-f", "1"], "f1")
 }
 
 #[test]
-fn three_count() -> TestResult {
-    run_count(&THREE)
+fn fields_skip_two_fields() -> TestResult {
+    run_flags(&FIELDS, &["--skip-fields", "2"], "f2")
 }
 
 #[test]
-fn three_stdin() -> TestResult {
-    run_stdin(&THREE)
+fn fields_skip_more_fields_than_exist() -> TestResult {
+    run_flags(&FIELDS, &["-f", "9"], "f9")
 }
 
 #[test]
-fn three_stdin_count() -> TestResult {
-    run_stdin_count(&THREE)
+fn fields_skip_chars() -> TestResult {
+    run_flags(&FIELDS, &["-s", "9"], "s9")
 }
 
 #[test]
-fn three_outfile() -> TestResult {
-    run_outfile(&THREE)
+fn fields_skip_fields_check_chars() -> TestResult {
+    run_flags(&FIELDS, &["-f", "1", "-w", "8"], "f1w8")
 }
 
 #[test]
-fn three_outfile_count() -> TestResult {
-    run_outfile_count(&THREE)
+fn fields_check_chars() -> TestResult {
+    run_flags(&FIELDS, &["--check-chars", "4"], "w4")
 }
 
 #[test]
-fn three_stdin_outfile_count() -> TestResult {
-    run_stdin_outfile_count(&THREE)
+fn fields_skip_fields_and_chars_count() -> TestResult {
+    run_flags(&FIELDS, &["-f", "1", "-s", "6", "-c"], "f1s6c")
 }
 
 // --------------------------------------------------
 #[test]
-fn skip() -> TestResult {
-    run(&SKIP)
+fn events() -> TestResult {
+    run(&EVENTS)
 }
 
 #[test]
-fn skip_count() -> TestResult {
-    run_count(&SKIP)
+fn events_count() -> TestResult {
+    run_count(&EVENTS)
 }
 
 #[test]
-fn skip_stdin() -> TestResult {
-    run_stdin(&SKIP)
+fn events_ignore_case() -> TestResult {
+    run_flags(&EVENTS, &["-i"], "i")
 }
 
 #[test]
-fn skip_stdin_count() -> TestResult {
-    run_stdin_count(&SKIP)
+fn events_collapse_whitespace() -> TestResult {
+    run_flags(&EVENTS, &["--collapse-whitespace"], "collapse")
 }
 
 #[test]
-fn skip_outfile() -> TestResult {
-    run_outfile(&SKIP)
+fn events_trim_collapse_whitespace() -> TestResult {
+    run_flags(&EVENTS, &["--trim", "--collapse-whitespace"], "trimcollapse")
 }
 
 #[test]
-fn skip_outfile_count() -> TestResult {
-    run_outfile_count(&SKIP)
+fn events_ignore_case_trim_collapse_whitespace_count() -> TestResult {
+    run_flags(
+        &EVENTS,
+        &["--ignore-case", "--trim", "--collapse-whitespace", "-c"],
+        "itrimcollapsec",
+    )
 }
 
 #[test]
-fn skip_stdin_outfile_count() -> TestResult {
-    run_stdin_outfile_count(&SKIP)
+fn events_normalize_nfc() -> TestResult {
+    run_flags(&EVENTS, &["--normalize=nfc"], "nfc")
 }
 
-// --------------------------------------------------
 #[test]
-fn t1() -> TestResult {
-    run(&T1)
+fn events_ignore_case_normalize_nfc_count() -> TestResult {
+    run_flags(&EVENTS, &["-i", "--normalize", "nfc", "-c"], "infcc")
 }
 
 #[test]
-fn t1_count() -> TestResult {
-    run_count(&T1)
+fn events_normalize_nfkc() -> TestResult {
+    run_flags(&EVENTS, &["--normalize=nfkc"], "nfkc")
 }
 
 #[test]
-fn t1_stdin() -> TestResult {
-    run_stdin(&T1)
+fn events_ignore_case_normalize_nfkc() -> TestResult {
+    run_flags(&EVENTS, &["-i", "--normalize=nfkc"], "infkc")
 }
 
+// --------------------------------------------------
 #[test]
-fn t1_stdin_count() -> TestResult {
-    run_stdin_count(&T1)
+fn global() -> TestResult {
+    run_flags(&GLOBAL, &["--global"], "global")
 }
 
 #[test]
-fn t1_outfile() -> TestResult {
-    run_outfile(&T1)
+fn global_count() -> TestResult {
+    run_flags(&GLOBAL, &["--global", "-c"], "globalc")
 }
 
 #[test]
-fn t1_outfile_count() -> TestResult {
-    run_outfile_count(&T1)
+fn global_repeated() -> TestResult {
+    run_flags(&GLOBAL, &["--global", "-d"], "globald")
 }
 
 #[test]
-fn t1_stdin_outfile_count() -> TestResult {
-    run_stdin_outfile_count(&T1)
+fn global_repeated_count() -> TestResult {
+    run_flags(&GLOBAL, &["--global", "-d", "-c"], "globaldc")
 }
 
-// --------------------------------------------------
 #[test]
-fn t2() -> TestResult {
-    run(&T2)
+fn global_unique() -> TestResult {
+    run_flags(&GLOBAL, &["--global", "-u"], "globalu")
 }
 
 #[test]
-fn t2_count() -> TestResult {
-    run_count(&T2)
+fn global_hash64() -> TestResult {
+    run_flags(&GLOBAL, &["--global", "--hash", "64"], "globalhash64")
 }
 
 #[test]
-fn t2_stdin() -> TestResult {
-    run_stdin(&T2)
+fn global_hash128_count() -> TestResult {
+    run_flags(&GLOBAL, &["--global", "--hash=128", "-c"], "globalhash128c")
 }
 
 #[test]
-fn t2_stdin_count() -> TestResult {
-    run_stdin_count(&T2)
+fn global_max_memory() -> TestResult {
+    run_flags(&GLOBAL, &["--global", "--max-memory", "1"], "global")
 }
 
 #[test]
-fn t2_outfile() -> TestResult {
-    run_outfile(&T2)
+fn global_max_memory_count() -> TestResult {
+    run_flags(&GLOBAL, &["--global", "--max-memory=1", "-c"], "globalc")
 }
 
 #[test]
-fn t2_outfile_count() -> TestResult {
-    run_outfile_count(&T2)
+fn events_global_ignore_case_normalize_nfkc_count() -> TestResult {
+    run_flags(
+        &EVENTS,
+        &["--global", "-i", "--normalize=nfkc", "-c"],
+        "globalinfkcc",
+    )
 }
 
+// --------------------------------------------------
 #[test]
-fn t2_stdin_outfile_count() -> TestResult {
-    run_stdin_outfile_count(&T2)
+fn three_top() -> TestResult {
+    run_flags(&THREE, &["--top", "2"], "top2")
 }
 
-// --------------------------------------------------
 #[test]
-fn t3() -> TestResult {
-    run(&T3)
+fn three_top_more_than_distinct() -> TestResult {
+    run_flags(&THREE, &["--top", "10"], "top10")
 }
 
 #[test]
-fn t3_count() -> TestResult {
-    run_count(&T3)
+fn three_bottom() -> TestResult {
+    run_flags(&THREE, &["--bottom", "2"], "bottom2")
 }
 
 #[test]
-fn t3_stdin() -> TestResult {
-    run_stdin(&T3)
+fn three_min_count() -> TestResult {
+    run_flags(&THREE, &["--min-count", "3"], "mincount3")
 }
 
 #[test]
-fn t3_stdin_count() -> TestResult {
-    run_stdin_count(&T3)
+fn three_top_min_count() -> TestResult {
+    run_flags(&THREE, &["--top", "2", "--min-count", "4"], "top2mincount4")
 }
 
 #[test]
-fn t3_outfile() -> TestResult {
-    run_outfile(&T3)
+fn three_bottom_min_count() -> TestResult {
+    run_flags(&THREE, &["--bottom", "3", "--min-count=3"], "bottom3mincount3")
 }
 
 #[test]
-fn t3_outfile_count() -> TestResult {
-    run_outfile_count(&T3)
+fn three_heavy_hitters() -> TestResult {
+    run_flags(&THREE, &["--heavy-hitters", "3"], "heavyhitters3")
 }
 
 #[test]
-fn t3_stdin_outfile_count() -> TestResult {
-    run_stdin_outfile_count(&T3)
+fn events_top() -> TestResult {
+    run_flags(
+        &EVENTS,
+        &[
+            "--top",
+            "3",
+            "-i",
+            "--trim",
+            "--collapse-whitespace",
+            "--normalize",
+            "nfkc",
+        ],
+        "top3",
+    )
 }
 
-// --------------------------------------------------
 #[test]
-fn t4() -> TestResult {
-    run(&T4)
+fn orders() -> TestResult {
+    run(&ORDERS)
 }
 
 #[test]
-fn t4_count() -> TestResult {
-    run_count(&T4)
+fn orders_count() -> TestResult {
+    run_count(&ORDERS)
 }
 
 #[test]
-fn t4_stdin() -> TestResult {
-    run_stdin(&T4)
+fn orders_key() -> TestResult {
+    run_flags(&ORDERS, &["--key", "customer_id,date", "--header"], "key")
 }
 
 #[test]
-fn t4_stdin_count() -> TestResult {
-    run_stdin_count(&T4)
+fn orders_key_count() -> TestResult {
+    run_flags(
+        &ORDERS,
+        &["--key", "customer_id,date", "--header", "-c"],
+        "keyc",
+    )
 }
 
 #[test]
-fn t4_outfile() -> TestResult {
-    run_outfile(&T4)
+fn orders_key_keep_last() -> TestResult {
+    run_flags(
+        &ORDERS,
+        &["--key", "customer_id,date", "--header", "--keep=last"],
+        "keylast",
+    )
 }
 
 #[test]
-fn t4_outfile_count() -> TestResult {
-    run_outfile_count(&T4)
+fn orders_key_global() -> TestResult {
+    run_flags(
+        &ORDERS,
+        &["--key", "customer_id,date", "--header", "--global"],
+        "keyglobal",
+    )
 }
 
 #[test]
-fn t4_stdin_outfile_count() -> TestResult {
-    run_stdin_outfile_count(&T4)
+fn orders_key_global_keep_last() -> TestResult {
+    run_flags(
+        &ORDERS,
+        &[
+            "--key",
+            "customer_id,date",
+            "--header",
+            "--global",
+            "--keep",
+            "last",
+        ],
+        "keygloballast",
+    )
 }
 
-// --------------------------------------------------
 #[test]
-fn t5() -> TestResult {
-    run(&T5)
+fn orders_key_index_trim() -> TestResult {
+    run_flags(
+        &ORDERS,
+        &["--key", "1", "--trim", "--global", "-c"],
+        "key1trimglobalc",
+    )
 }
 
 #[test]
-fn t5_count() -> TestResult {
-    run_count(&T5)
+fn orders_key_indexes_repeated() -> TestResult {
+    run_flags(&ORDERS, &["--key", "1,2", "-d"], "key12d")
 }
 
 #[test]
-fn t5_stdin() -> TestResult {
-    run_stdin(&T5)
+fn orders_key_top() -> TestResult {
+    run_flags(&ORDERS, &["--key", "date", "--header", "--top", "1"], "keytop1")
 }
 
 #[test]
-fn t5_stdin_count() -> TestResult {
-    run_stdin_count(&T5)
+fn people() -> TestResult {
+    run(&PEOPLE)
 }
 
 #[test]
-fn t5_outfile() -> TestResult {
-    run_outfile(&T5)
+fn people_count() -> TestResult {
+    run_count(&PEOPLE)
 }
 
 #[test]
-fn t5_outfile_count() -> TestResult {
-    run_outfile_count(&T5)
+fn people_key_ignore_case() -> TestResult {
+    run_flags(
+        &PEOPLE,
+        &["--key", "name", "--header", "-i", "--delimiter", "\t"],
+        "keyi",
+    )
 }
 
 #[test]
-fn t5_stdin_outfile_count() -> TestResult {
-    run_stdin_outfile_count(&T5)
+fn people_key_global_keep_last() -> TestResult {
+    run_flags(
+        &PEOPLE,
+        &[
+            "--key=name",
+            "--header",
+            "-i",
+            "--global",
+            "--keep=last",
+            "--delimiter=\t",
+        ],
+        "keyigloballast",
+    )
 }
 
-// --------------------------------------------------
 #[test]
-fn t6() -> TestResult {
-    run(&T6)
+fn binary() -> TestResult {
+    run(&BINARY)
 }
 
 #[test]
-fn t6_count() -> TestResult {
-    run_count(&T6)
+fn binary_count() -> TestResult {
+    run_count(&BINARY)
 }
 
 #[test]
-fn t6_stdin() -> TestResult {
-    run_stdin(&T6)
+fn binary_stdin() -> TestResult {
+    run_stdin(&BINARY)
 }
 
 #[test]
-fn t6_stdin_count() -> TestResult {
-    run_stdin_count(&T6)
+fn binary_outfile_count() -> TestResult {
+    run_outfile_count(&BINARY)
 }
 
 #[test]
-fn t6_outfile() -> TestResult {
-    run_outfile(&T6)
+fn binary_ignore_case_collapse_whitespace() -> TestResult {
+    run_flags(&BINARY, &["-c", "-i", "--collapse-whitespace"], "icollapsec")
 }
 
 #[test]
-fn t6_outfile_count() -> TestResult {
-    run_outfile_count(&T6)
+fn binary_skip_check_chars() -> TestResult {
+    run_flags(&BINARY, &["-c", "-s", "6", "-w", "1"], "s6w1c")
 }
 
 #[test]
-fn t6_stdin_outfile_count() -> TestResult {
-    run_stdin_outfile_count(&T6)
+fn binary_global_skip_fields() -> TestResult {
+    run_flags(&BINARY, &["--global", "-c", "-i", "-f", "1"], "globalif1c")
+}
+
+#[test]
+fn binary_top() -> TestResult {
+    run_flags(&BINARY, &["--top", "2", "--trim", "-i"], "top2")
 }
@@ -0,0 +1,96 @@
+// Throughput of `wcr::count_with` on a few kinds of text, next to a
+// plain line-at-a-time count of the same measures for comparison. Run with
+// `cargo bench`; there is no harness, so every argument is ignored.
+use std::{
+    hint::black_box,
+    io::{BufRead, Cursor},
+    time::{Duration, Instant},
+};
+use unicode_width::UnicodeWidthChar;
+use wcr::Measure;
+
+const SIZE: usize = 32 * 1024 * 1024;
+
+// Only what `read_lines` counts too, leaving out segmentation
+const MEASURE: Measure = Measure {
+    words: true,
+    chars: true,
+    widths: true,
+    unicode_words: false,
+    graphemes: false,
+};
+
+fn main() {
+    let inputs = [
+        (
+            "prose",
+            repeat_to_size("The quick brown fox jumps over the lazy dog.\n"),
+        ),
+        (
+            "long lines",
+            repeat_to_size(&"lorem ipsum dolor sit amet ".repeat(200)),
+        ),
+        (
+            "multilingual",
+            repeat_to_size("Grüße, 世界! Ça va? 🦀 Привет мир\n"),
+        ),
+        ("code", repeat_to_size("\tif x > 0 {\n\t\treturn x * 2;\n\t}\n")),
+    ];
+
+    for (name, text) in &inputs {
+        bench(&format!("count_with/{}", name), text, |text| {
+            wcr::count_with(Cursor::new(text), MEASURE).unwrap();
+        });
+        bench(&format!("read_line/{}", name), text, |text| {
+            read_lines(Cursor::new(text));
+        });
+    }
+}
+
+fn repeat_to_size(text: &str) -> Vec<u8> {
+    text.repeat(SIZE / text.len() + 1).into_bytes()
+}
+
+// What `count` did before it scanned raw buffers
+fn read_lines(mut file: impl BufRead) -> (usize, usize, usize, usize) {
+    let (mut lines, mut words, mut chars, mut max_width) = (0, 0, 0, 0);
+    let mut line = String::new();
+    while file.read_line(&mut line).unwrap() > 0 {
+        lines += 1;
+        words += line.split_whitespace().count();
+        chars += line.chars().count();
+        max_width = max_width.max(line_width(&line));
+        line.clear();
+    }
+    (lines, words, chars, max_width)
+}
+
+fn line_width(line: &str) -> usize {
+    let mut width = 0;
+    let mut column = 0;
+    for c in line.chars() {
+        match c {
+            '\n' | '\r' | '\x0c' => {
+                width = width.max(column);
+                column = 0;
+            }
+            '\t' => column += 8 - column % 8,
+            _ => column += c.width().unwrap_or(0),
+        }
+    }
+    width.max(column)
+}
+
+// Run `f` for at least a second and report the best throughput
+fn bench(name: &str, text: &[u8], f: impl Fn(&[u8])) {
+    f(black_box(text));
+    let mut best = Duration::MAX;
+    let started = Instant::now();
+    while started.elapsed() < Duration::from_secs(1) {
+        let start = Instant::now();
+        f(black_box(text));
+        best = best.min(start.elapsed());
+    }
+    let mib = text.len() as f64 / (1024.0 * 1024.0);
+    println!("{:<24} {:>8.0} MiB/s", name, mib / best.as_secs_f64());
+}
@@ -0,0 +1,399 @@
+use crate::FileInfo;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use std::{
+    io::{self, BufRead},
+    path::{Path, PathBuf},
+};
+use walkdir::WalkDir;
+
+// How a language writes comments and strings. Strings are only
+// followed within a line, so that a comment marker inside one
+// is not taken for a comment.
+#[derive(Debug)]
+pub struct Language {
+    pub name: &'static str,
+    extensions: &'static [&'static str],
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    nested: bool,
+    quotes: &'static [u8],
+}
+
+const C_BLOCK: Option<(&str, &str)> = Some(("/*", "*/"));
+
+const LANGUAGES: &[Language] = &[
+    Language {
+        name: "C",
+        extensions: &["c", "h"],
+        line_comments: &["//"],
+        block_comment: C_BLOCK,
+        nested: false,
+        quotes: b"\"'",
+    },
+    Language {
+        name: "C#",
+        extensions: &["cs"],
+        line_comments: &["//"],
+        block_comment: C_BLOCK,
+        nested: false,
+        quotes: b"\"'",
+    },
+    Language {
+        name: "C++",
+        extensions: &["cc", "cpp", "cxx", "hh", "hpp", "hxx"],
+        line_comments: &["//"],
+        block_comment: C_BLOCK,
+        nested: false,
+        quotes: b"\"'",
+    },
+    Language {
+        name: "CSS",
+        extensions: &["css"],
+        line_comments: &[],
+        block_comment: C_BLOCK,
+        nested: false,
+        quotes: b"\"'",
+    },
+    Language {
+        name: "Go",
+        extensions: &["go"],
+        line_comments: &["//"],
+        block_comment: C_BLOCK,
+        nested: false,
+        quotes: b"\"'`",
+    },
+    Language {
+        name: "HTML",
+        extensions: &["htm", "html"],
+        line_comments: &[],
+        block_comment: Some(("<!--", "-->")),
+        nested: false,
+        quotes: b"",
+    },
+    Language {
+        name: "Haskell",
+        extensions: &["hs"],
+        line_comments: &["--"],
+        block_comment: Some(("{-", "-}")),
+        nested: true,
+        quotes: b"\"",
+    },
+    Language {
+        name: "Java",
+        extensions: &["java"],
+        line_comments: &["//"],
+        block_comment: C_BLOCK,
+        nested: false,
+        quotes: b"\"'",
+    },
+    Language {
+        name: "JavaScript",
+        extensions: &["cjs", "js", "jsx", "mjs"],
+        line_comments: &["//"],
+        block_comment: C_BLOCK,
+        nested: false,
+        quotes: b"\"'`",
+    },
+    Language {
+        name: "Lua",
+        extensions: &["lua"],
+        line_comments: &["--"],
+        block_comment: Some(("--[[", "]]")),
+        nested: false,
+        quotes: b"\"'",
+    },
+    Language {
+        name: "Python",
+        extensions: &["py"],
+        line_comments: &["#"],
+        block_comment: None,
+        nested: false,
+        quotes: b"\"'",
+    },
+    Language {
+        name: "Ruby",
+        extensions: &["rb"],
+        line_comments: &["#"],
+        block_comment: None,
+        nested: false,
+        quotes: b"\"'",
+    },
+    Language {
+        name: "Rust",
+        extensions: &["rs"],
+        line_comments: &["//"],
+        block_comment: C_BLOCK,
+        nested: true,
+        quotes: b"\"",
+    },
+    Language {
+        name: "SQL",
+        extensions: &["sql"],
+        line_comments: &["--"],
+        block_comment: C_BLOCK,
+        nested: false,
+        quotes: b"\"'",
+    },
+    Language {
+        name: "Shell",
+        extensions: &["bash", "sh", "zsh"],
+        line_comments: &["#"],
+        block_comment: None,
+        nested: false,
+        quotes: b"\"'",
+    },
+    Language {
+        name: "TOML",
+        extensions: &["toml"],
+        line_comments: &["#"],
+        block_comment: None,
+        nested: false,
+        quotes: b"\"'",
+    },
+    Language {
+        name: "TypeScript",
+        extensions: &["ts", "tsx"],
+        line_comments: &["//"],
+        block_comment: C_BLOCK,
+        nested: false,
+        quotes: b"\"'`",
+    },
+    Language {
+        name: "YAML",
+        extensions: &["yaml", "yml"],
+        line_comments: &["#"],
+        block_comment: None,
+        nested: false,
+        quotes: b"\"'",
+    },
+];
+
+// The language of a file, going by its extension
+pub fn detect(filename: &Path) -> Option<&'static Language> {
+    let extension = filename.extension()?.to_str()?;
+    LANGUAGES.iter().find(|language| {
+        language.extensions.iter().any(|e| e.eq_ignore_ascii_case(extension))
+    })
+}
+
+#[derive(Debug, PartialEq)]
+enum Kind {
+    Blank,
+    Comment,
+    Code,
+}
+
+// Only the lines and how they divide into blank, comment and code
+// are counted
+pub fn count(
+    language: &Language,
+    mut file: impl BufRead,
+) -> io::Result<FileInfo> {
+    let mut info = FileInfo::default();
+    let mut depth = 0;
+    let mut line = vec![];
+    while file.read_until(b'\n', &mut line)? > 0 {
+        info.num_lines += 1;
+        match classify(language, &line, &mut depth) {
+            Kind::Blank => info.num_blank += 1,
+            Kind::Comment => info.num_comment += 1,
+            Kind::Code => info.num_code += 1,
+        }
+        line.clear();
+    }
+    Ok(info)
+}
+
+// A line is code if anything but whitespace is outside a comment,
+// a comment if it has anything else, and blank otherwise, even
+// inside a block comment. `depth` is how many block comments are
+// open, and is carried from one line to the next.
+fn classify(language: &Language, line: &[u8], depth: &mut usize) -> Kind {
+    if line.iter().all(u8::is_ascii_whitespace) {
+        return Kind::Blank;
+    }
+
+    // Empty markers for a language without block comments
+    let (start, end) = match language.block_comment {
+        Some((start, end)) => (start.as_bytes(), end.as_bytes()),
+        None => (&b""[..], &b""[..]),
+    };
+    let opens = |rest: &[u8]| !start.is_empty() && rest.starts_with(start);
+    let closes = |rest: &[u8]| !end.is_empty() && rest.starts_with(end);
+
+    let mut code = false;
+    let mut quote = None;
+    let mut i = 0;
+    while i < line.len() {
+        let rest = &line[i..];
+        if let Some(q) = quote {
+            if rest[0] == b'\\' {
+                i += 1;
+            } else if rest[0] == q {
+                quote = None;
+            }
+            i += 1;
+        } else if *depth > 0 && closes(rest) {
+            *depth -= 1;
+            i += end.len();
+        } else if (*depth == 0 || language.nested) && opens(rest) {
+            *depth += 1;
+            i += start.len();
+        } else if *depth > 0 {
+            i += 1;
+        } else if language
+            .line_comments
+            .iter()
+            .any(|c| rest.starts_with(c.as_bytes()))
+        {
+            break;
+        } else {
+            if language.quotes.contains(&rest[0]) {
+                quote = Some(rest[0]);
+            }
+            code |= !rest[0].is_ascii_whitespace();
+            i += 1;
+        }
+    }
+
+    if code {
+        Kind::Code
+    } else {
+        Kind::Comment
+    }
+}
+
+// Patterns for `--exclude`. One with a slash is matched against the
+// whole path, and one without against the last component, so `target`
+// skips every directory of that name while `src/*.rs` only skips some
+// files. `*` and `?` stay within a component, while `**` crosses them.
+pub struct Exclude {
+    paths: GlobSet,
+    names: GlobSet,
+}
+
+impl Exclude {
+    pub fn new(patterns: &[String]) -> Result<Self, String> {
+        let mut paths = GlobSetBuilder::new();
+        let mut names = GlobSetBuilder::new();
+        for pattern in patterns {
+            let glob = pattern.trim_start_matches("./").trim_end_matches('/');
+            let set = if glob.contains('/') { &mut paths } else { &mut names };
+            set.add(
+                GlobBuilder::new(glob)
+                    .literal_separator(true)
+                    .build()
+                    .map_err(|e| {
+                        format!(
+                            "invalid --exclude \"{}\": {}",
+                            pattern,
+                            e.kind()
+                        )
+                    })?,
+            );
+        }
+        let build =
+            |set: GlobSetBuilder| set.build().map_err(|e| e.to_string());
+        Ok(Exclude { paths: build(paths)?, names: build(names)? })
+    }
+
+    fn matches(&self, path: &Path) -> bool {
+        let path = path.strip_prefix(".").unwrap_or(path);
+        self.paths.is_match(path)
+            || path.file_name().is_some_and(|name| self.names.is_match(name))
+    }
+}
+
+// The files under `paths` that are in a known language, walking
+// directories recursively in name order. Symbolic links to
+// directories are not followed, to keep clear of cycles.
+pub fn find_sources(paths: &[PathBuf], exclude: &Exclude) -> Vec<PathBuf> {
+    let mut files = vec![];
+    for path in paths {
+        let entries = WalkDir::new(path)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|entry| !exclude.matches(entry.path()));
+        for entry in entries {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    let path = e.path().unwrap_or(path).display();
+                    match e.io_error() {
+                        Some(err) => eprintln!("{}: {}", path, err),
+                        None => eprintln!("{}: {}", path, e),
+                    }
+                    continue;
+                }
+            };
+            let is_file = entry.file_type().is_file()
+                || (entry.path_is_symlink() && entry.path().is_file());
+            if is_file && detect(entry.path()).is_some() {
+                files.push(entry.into_path());
+            }
+        }
+    }
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{classify, detect, Exclude, Kind};
+    use std::path::Path;
+
+    fn classify_all(extension: &str, text: &str) -> Vec<Kind> {
+        let name = format!("file.{}", extension);
+        let language = detect(Path::new(&name)).unwrap();
+        let mut depth = 0;
+        text.lines()
+            .map(|line| classify(language, line.as_bytes(), &mut depth))
+            .collect()
+    }
+
+    #[test]
+    fn test_classify() {
+        use Kind::*;
+        assert_eq!(
+            classify_all("rs", "// a\n\nx /* a\n/* b */ */\n \t\ny"),
+            [Comment, Blank, Code, Comment, Blank, Code],
+        );
+        assert_eq!(
+            classify_all("c", "/* a /* b */\nx\n\"/*\"\n'\\''//"),
+            [Comment, Code, Code, Code],
+        );
+        assert_eq!(
+            classify_all("py", "# a\n  # b\ns = '#'\n"),
+            [Comment, Comment, Code],
+        );
+        assert_eq!(
+            classify_all("lua", "--[[ a\n]] x\n-- b"),
+            [Comment, Code, Comment],
+        );
+        assert!(detect(Path::new("Makefile")).is_none());
+        assert_eq!(detect(Path::new("MAIN.RS")).map(|l| l.name), Some("Rust"));
+    }
+
+    #[test]
+    fn test_exclude() {
+        let patterns = ["target", "src/*.py", "**/gen/**", "[!a-c]x.rs"]
+            .map(String::from);
+        let exclude = Exclude::new(&patterns).unwrap();
+        assert!(exclude.matches(Path::new("./target")));
+        assert!(exclude.matches(Path::new("a/b/target/")));
+        assert!(exclude.matches(Path::new("./src/x.py")));
+        assert!(!exclude.matches(Path::new("lib/src/x.py")));
+        assert!(!exclude.matches(Path::new("src/a/x.py")));
+        assert!(exclude.matches(Path::new("a/gen/b/c.rs")));
+        assert!(exclude.matches(Path::new("dx.rs")));
+        assert!(!exclude.matches(Path::new("bx.rs")));
+        assert!(!exclude.matches(Path::new(".")));
+
+        // Repeated stars do not backtrack without end
+        let patterns = ["**/*a*a*a*a*a*a*a*a*b".to_string()];
+        let exclude = Exclude::new(&patterns).unwrap();
+        let path = format!("{}/{}", "a/".repeat(100), "a".repeat(200));
+        assert!(!exclude.matches(Path::new(&path)));
+
+        assert!(Exclude::new(&["a[".to_string()]).is_err());
+    }
+}
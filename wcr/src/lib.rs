@@ -1,27 +1,238 @@
+mod code;
+mod words;
+
 use clap::{App, Arg};
+use memchr::memchr_iter;
 use std::{
+    collections::{BTreeMap, HashMap},
     error::Error,
     fs::File,
     io::{self, BufRead, BufReader, Read},
+    iter::Sum,
+    num::NonZeroUsize,
+    ops::{Add, AddAssign},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
 };
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthChar;
+use words::{WordCounts, WordRules};
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
+// Bytes read at a time, large enough to amortize each read call
+const BUFFER_SIZE: usize = 256 * 1024;
+
 #[derive(Debug)]
 pub struct Config {
-    files: Vec<String>,
+    files: Vec<PathBuf>,
+    files_from: Option<NameList>,
     lines: bool,
     words: bool,
+    unicode_words: bool,
     bytes: bool,
     chars: bool,
+    graphemes: bool,
+    max_line_length: bool,
+    line_stats: bool,
+    total: Total,
+    code: bool,
+    exclude: Vec<String>,
+    top_words: Option<usize>,
+    ignore_case: bool,
+    stopwords: Option<String>,
+    per_file: bool,
+}
+
+// When to print the `total` row: for more than one file, always,
+// on its own, or never
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Total {
+    Auto,
+    Always,
+    Only,
+    Never,
+}
+
+// A file holding the names of the files to count, one per line or
+// separated by NUL bytes
+#[derive(Debug)]
+pub struct NameList {
+    file: PathBuf,
+    separator: u8,
 }
 
-#[derive(Debug, PartialEq)]
+/// The counts for one file, or the total of several, as added up
+/// with `+` or `sum()`. Anything a [`Counter`] was not asked to
+/// measure is zero.
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct FileInfo {
     num_lines: usize,
     num_words: usize,
+    num_unicode_words: usize,
     num_bytes: usize,
     num_chars: usize,
+    num_graphemes: usize,
+    max_line_length: usize,
+    line_stats: LineStats,
+    num_blank: usize,
+    num_comment: usize,
+    num_code: usize,
+}
+
+impl FileInfo {
+    /// Newlines, plus one for a last line without a newline
+    pub fn lines(&self) -> usize {
+        self.num_lines
+    }
+
+    /// Runs of anything but whitespace
+    pub fn words(&self) -> usize {
+        self.num_words
+    }
+
+    /// Words found at Unicode word boundaries, which leave out
+    /// punctuation and split text without spaces such as Chinese
+    pub fn unicode_words(&self) -> usize {
+        self.num_unicode_words
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.num_bytes
+    }
+
+    /// Valid UTF-8 scalar values; bytes that are not part of one are
+    /// not counted
+    pub fn chars(&self) -> usize {
+        self.num_chars
+    }
+
+    /// Extended grapheme clusters, or user-perceived characters
+    pub fn graphemes(&self) -> usize {
+        self.num_graphemes
+    }
+
+    /// The display width of the widest line, as `wc -L` has it
+    pub fn max_line_length(&self) -> usize {
+        self.max_line_length
+    }
+
+    pub fn line_stats(&self) -> &LineStats {
+        &self.line_stats
+    }
+
+    /// Lines of nothing but whitespace, when counted as source code
+    pub fn blank_lines(&self) -> usize {
+        self.num_blank
+    }
+
+    /// Lines with nothing but comments and whitespace, when counted
+    /// as source code
+    pub fn comment_lines(&self) -> usize {
+        self.num_comment
+    }
+
+    /// Lines with anything outside a comment, when counted as source
+    /// code
+    pub fn code_lines(&self) -> usize {
+        self.num_code
+    }
+}
+
+impl AddAssign<&FileInfo> for FileInfo {
+    fn add_assign(&mut self, other: &FileInfo) {
+        self.num_lines += other.num_lines;
+        self.num_words += other.num_words;
+        self.num_unicode_words += other.num_unicode_words;
+        self.num_bytes += other.num_bytes;
+        self.num_chars += other.num_chars;
+        self.num_graphemes += other.num_graphemes;
+        self.max_line_length = self.max_line_length.max(other.max_line_length);
+        self.line_stats.merge(&other.line_stats);
+        self.num_blank += other.num_blank;
+        self.num_comment += other.num_comment;
+        self.num_code += other.num_code;
+    }
+}
+
+impl AddAssign for FileInfo {
+    fn add_assign(&mut self, other: FileInfo) {
+        *self += &other;
+    }
+}
+
+impl Add for FileInfo {
+    type Output = FileInfo;
+
+    fn add(mut self, other: FileInfo) -> FileInfo {
+        self += &other;
+        self
+    }
+}
+
+impl Sum for FileInfo {
+    fn sum<I: Iterator<Item = FileInfo>>(iter: I) -> FileInfo {
+        iter.fold(FileInfo::default(), Add::add)
+    }
+}
+
+impl<'a> Sum<&'a FileInfo> for FileInfo {
+    fn sum<I: Iterator<Item = &'a FileInfo>>(iter: I) -> FileInfo {
+        iter.fold(FileInfo::default(), |mut total, info| {
+            total += info;
+            total
+        })
+    }
+}
+
+/// How many lines there are of each display width
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct LineStats {
+    widths: BTreeMap<usize, usize>,
+}
+
+impl LineStats {
+    fn add(&mut self, width: usize) {
+        *self.widths.entry(width).or_default() += 1;
+    }
+
+    fn merge(&mut self, other: &LineStats) {
+        for (&width, &n) in &other.widths {
+            *self.widths.entry(width).or_default() += n;
+        }
+    }
+
+    /// The width of the narrowest line, or None without lines
+    pub fn min(&self) -> Option<usize> {
+        self.widths.keys().next().copied()
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        let (sum, n) = self
+            .widths
+            .iter()
+            .fold((0, 0), |(sum, n), (w, count)| (sum + w * count, n + count));
+        (n > 0).then(|| sum as f64 / n as f64)
+    }
+
+    /// The nearest-rank percentile for `p` from 0 to 100, so always
+    /// the width of some line
+    pub fn percentile(&self, p: usize) -> Option<usize> {
+        let n: usize = self.widths.values().sum();
+        let rank = (n * p).div_ceil(100).max(1);
+        let mut seen = 0;
+        for (&width, &count) in &self.widths {
+            seen += count;
+            if seen >= rank {
+                return Some(width);
+            }
+        }
+        None
+    }
 }
 
 pub fn get_args() -> MyResult<Config> {
@@ -32,10 +243,29 @@ pub fn get_args() -> MyResult<Config> {
         .arg(
             Arg::with_name("files")
                 .value_name("FILE")
-                .help("Input file(s)")
-                .default_value("-")
+                .help("Input file(s) [default: -, or . with --code]")
                 .multiple(true),
         )
+        .arg(
+            Arg::with_name("files0_from")
+                .long("files0-from")
+                .value_name("F")
+                .help(
+                    "Read the names of the input files from F, separated \
+                     by NUL bytes; - reads them from stdin",
+                )
+                .conflicts_with_all(&["files", "files_from"]),
+        )
+        .arg(
+            Arg::with_name("files_from")
+                .long("files-from")
+                .value_name("F")
+                .help(
+                    "Read the names of the input files from F, one per \
+                     line; - reads them from stdin",
+                )
+                .conflicts_with("files"),
+        )
         .arg(
             Arg::with_name("words")
                 .short("w")
@@ -65,131 +295,1233 @@ pub fn get_args() -> MyResult<Config> {
                 .help("Show line count")
                 .takes_value(false),
         )
+        .arg(
+            Arg::with_name("max_line_length")
+                .short("L")
+                .long("max-line-length")
+                .help("Show the display width of the longest line")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("line_stats")
+                .long("line-stats")
+                .help("Show the min, mean, median and p95 line width")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("total")
+                .long("total")
+                .value_name("WHEN")
+                .help("When to print a total [default: auto]")
+                .possible_values(&["auto", "always", "only", "never"])
+                .conflicts_with("top_words"),
+        )
+        .arg(
+            Arg::with_name("code")
+                .long("code")
+                .help(
+                    "Count blank, comment and code lines by language, \
+                     walking directories recursively",
+                )
+                .takes_value(false)
+                .conflicts_with_all(&[
+                    "words",
+                    "bytes",
+                    "chars",
+                    "lines",
+                    "max_line_length",
+                    "line_stats",
+                    "unicode_words",
+                    "graphemes",
+                ]),
+        )
+        .arg(
+            Arg::with_name("exclude")
+                .long("exclude")
+                .value_name("GLOB")
+                .help(
+                    "Skip files and directories matching GLOB, by name \
+                     or by path if it has a slash",
+                )
+                .multiple(true)
+                .number_of_values(1)
+                .requires("code"),
+        )
+        .arg(
+            Arg::with_name("top_words")
+                .long("top-words")
+                .value_name("N")
+                .help("Show the N most frequent words and their counts")
+                .conflicts_with_all(&[
+                    "words",
+                    "bytes",
+                    "chars",
+                    "lines",
+                    "max_line_length",
+                    "line_stats",
+                    "graphemes",
+                    "code",
+                ]),
+        )
+        .arg(
+            Arg::with_name("unicode_words")
+                .long("unicode-words")
+                .help(
+                    "Show the count of words split at Unicode word \
+                     boundaries, which leave out punctuation; with \
+                     --top-words, rank these words",
+                )
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("graphemes")
+                .long("graphemes")
+                .help("Show the count of user-perceived characters")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("ignore_case")
+                .long("ignore-case")
+                .help("Fold words to lowercase before counting them")
+                .takes_value(false)
+                .requires("top_words"),
+        )
+        .arg(
+            Arg::with_name("stopwords")
+                .long("stopwords")
+                .value_name("FILE")
+                .help("Leave out the whitespace-separated words in FILE")
+                .requires("top_words"),
+        )
+        .arg(
+            Arg::with_name("per_file")
+                .long("per-file")
+                .help("Show the most frequent words of each file as well")
+                .takes_value(false)
+                .requires("top_words"),
+        )
         .get_matches();
 
     let mut lines = matches.is_present("lines");
     let mut words = matches.is_present("words");
     let mut bytes = matches.is_present("bytes");
     let chars = matches.is_present("chars");
+    let unicode_words = matches.is_present("unicode_words");
+    let graphemes = matches.is_present("graphemes");
+    let max_line_length = matches.is_present("max_line_length");
+    let line_stats = matches.is_present("line_stats");
+    let code = matches.is_present("code");
+    let top_words = matches
+        .value_of("top_words")
+        .map(parse_positive_int)
+        .transpose()
+        .map_err(|e| format!("illegal word count -- {}", e))?;
 
-    if [words, bytes, chars, lines].iter().all(|v| v == &false) {
+    if [
+        words,
+        unicode_words,
+        bytes,
+        chars,
+        graphemes,
+        lines,
+        max_line_length,
+        line_stats,
+    ]
+    .iter()
+    .all(|v| v == &false)
+    {
         lines = true;
         words = true;
         bytes = true;
     }
 
+    let files_from = match (
+        matches.value_of_os("files0_from"),
+        matches.value_of_os("files_from"),
+    ) {
+        (Some(file), _) => Some(NameList { file: file.into(), separator: 0 }),
+        (_, Some(file)) => {
+            Some(NameList { file: file.into(), separator: b'\n' })
+        }
+        _ => None,
+    };
+
     Ok(Config {
-        files: matches.values_of_lossy("files").unwrap(),
+        files: matches.values_of_os("files").map_or_else(
+            || vec![PathBuf::from(if code { "." } else { "-" })],
+            |files| files.map(PathBuf::from).collect(),
+        ),
+        files_from,
         lines,
         words,
+        unicode_words,
         bytes,
         chars,
+        graphemes,
+        max_line_length,
+        line_stats,
+        total: match matches.value_of("total") {
+            Some("always") => Total::Always,
+            Some("only") => Total::Only,
+            Some("never") => Total::Never,
+            _ => Total::Auto,
+        },
+        code,
+        exclude: matches.values_of_lossy("exclude").unwrap_or_default(),
+        top_words,
+        ignore_case: matches.is_present("ignore_case"),
+        stopwords: matches.value_of("stopwords").map(String::from),
+        per_file: matches.is_present("per_file"),
     })
 }
 
+fn parse_positive_int(val: &str) -> MyResult<usize> {
+    match val.parse() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(val.into()),
+    }
+}
+
 pub fn run(config: Config) -> MyResult<()> {
-    let mut total_lines = 0;
-    let mut total_words = 0;
-    let mut total_bytes = 0;
-    let mut total_chars = 0;
-
-    for filename in &config.files {
-        match open(filename) {
-            Err(err) => eprintln!("{}: {}", filename, err),
-            Ok(file) => {
-                if let Ok(info) = count(file) {
-                    println!(
-                        "{}{}{}{}{}",
-                        format_field(info.num_lines, config.lines),
-                        format_field(info.num_words, config.words),
-                        format_field(info.num_bytes, config.bytes),
-                        format_field(info.num_chars, config.chars),
-                        if filename == "-" {
-                            "".to_string()
-                        } else {
-                            format!(" {}", filename)
-                        }
-                    );
-                    total_lines += info.num_lines;
-                    total_words += info.num_words;
-                    total_bytes += info.num_bytes;
-                    total_chars += info.num_chars;
+    let measure = Measure {
+        words: config.words,
+        chars: config.chars,
+        widths: config.max_line_length || config.line_stats,
+        unicode_words: config.unicode_words,
+        graphemes: config.graphemes,
+    };
+    let files = match &config.files_from {
+        Some(list) => read_names(list)?,
+        None => config.files.clone(),
+    };
+    // Once stdin has been read for names it has no data left
+    let stdin_read =
+        config.files_from.as_ref().is_some_and(|l| l.file.as_os_str() == "-");
+
+    if config.code {
+        return run_code(&files, &config, stdin_read);
+    }
+    if let Some(n) = config.top_words {
+        return run_top_words(&files, n, &config, stdin_read);
+    }
+
+    // Every row is held back until the widest value is known
+    let mut rows = vec![];
+    let mut total = FileInfo::default();
+    count_files(
+        &files,
+        stdin_read,
+        |_, file| count_with(file, measure),
+        |filename, result| match result {
+            Err(err) => eprintln!("{}", err),
+            Ok(info) => {
+                let row = fields(&info, &config);
+                total += info;
+                if config.total != Total::Only {
+                    let name = if filename.as_os_str() == "-" {
+                        "".to_string()
+                    } else {
+                        format!(" {}", filename.display())
+                    };
+                    rows.push((row, name));
                 }
             }
-        }
+        },
+    );
+
+    // Like GNU wc, the total has no label when it is all there is
+    let label = match config.total {
+        Total::Auto if files.len() > 1 => Some(" total"),
+        Total::Always => Some(" total"),
+        Total::Only => Some(""),
+        _ => None,
+    };
+    if let Some(label) = label {
+        rows.push((fields(&total, &config), label.to_string()));
     }
-    if config.files.len() > 1 {
-        println!(
-            "{}{}{}{} total",
-            format_field(total_lines, config.lines),
-            format_field(total_words, config.words),
-            format_field(total_bytes, config.bytes),
-            format_field(total_chars, config.chars),
-        )
+
+    let width = column_width(rows.iter().flat_map(|(fields, _)| fields));
+    for (fields, name) in rows {
+        let fields: String =
+            fields.iter().map(|field| format_field(field, width)).collect();
+        println!("{}{}", fields, name);
+    }
+    Ok(())
+}
+
+// Tally the files in each language under `paths`, with a total
+// when there is more than one language
+fn run_code(
+    paths: &[PathBuf],
+    config: &Config,
+    stdin_read: bool,
+) -> MyResult<()> {
+    let exclude = code::Exclude::new(&config.exclude)?;
+    let files = code::find_sources(paths, &exclude);
+    let mut languages = BTreeMap::new();
+    let mut total = (0, FileInfo::default());
+    count_files(
+        &files,
+        stdin_read,
+        |filename, file| match code::detect(filename) {
+            Some(language) => {
+                Ok(Some((language.name, code::count(language, file)?)))
+            }
+            None => Ok(None),
+        },
+        |_, result| match result {
+            Err(err) => eprintln!("{}", err),
+            Ok(Some((name, info))) => {
+                let (files, sum) = languages
+                    .entry(name)
+                    .or_insert_with(|| (0, FileInfo::default()));
+                *files += 1;
+                *sum += &info;
+                total.0 += 1;
+                total.1 += info;
+            }
+            Ok(None) => {}
+        },
+    );
+
+    let code_fields = |(files, info): &(usize, FileInfo)| {
+        [
+            *files,
+            info.num_lines,
+            info.num_blank,
+            info.num_comment,
+            info.num_code,
+        ]
+        .map(|value| value.to_string())
+    };
+    let header = ["Files", "Lines", "Blank", "Comment", "Code"];
+    let mut rows = vec![("Language", header.map(String::from))];
+    if config.total != Total::Only {
+        rows.extend(
+            languages
+                .iter()
+                .map(|(name, counts)| (*name, code_fields(counts))),
+        );
+    }
+    let show_total = match config.total {
+        Total::Auto => languages.len() > 1,
+        Total::Never => false,
+        _ => true,
+    };
+    if show_total {
+        rows.push(("Total", code_fields(&total)));
+    }
+
+    let width = column_width(rows.iter().flat_map(|(_, fields)| fields));
+    for (name, fields) in rows {
+        let fields: String =
+            fields.iter().map(|field| format_field(field, width)).collect();
+        println!("{:<12}{}", name, fields);
     }
     Ok(())
 }
 
-fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
-    match filename {
-        "-" => Ok(Box::new(BufReader::new(io::stdin()))),
-        _ => Ok(Box::new(BufReader::new(File::open(filename)?))),
+// Print the most frequent words across the files, and in each
+// file as well with `--per-file`
+fn run_top_words(
+    files: &[PathBuf],
+    n: usize,
+    config: &Config,
+    stdin_read: bool,
+) -> MyResult<()> {
+    let mut rules = WordRules {
+        unicode: config.unicode_words,
+        ignore_case: config.ignore_case,
+        ..Default::default()
+    };
+    if let Some(filename) = &config.stopwords {
+        let mut text = String::new();
+        open(Path::new(filename))
+            .and_then(|mut file| Ok(file.read_to_string(&mut text)?))
+            .map_err(|e| format!("{}: {}", filename, e))?;
+        rules.add_stopwords(&text);
+    }
+
+    let mut total = WordCounts::new();
+    let mut printed = false;
+    let mut print = |name: Option<&str>, counts: &WordCounts| {
+        if let Some(name) = name {
+            println!("{}==> {} <==", if printed { "\n" } else { "" }, name);
+        }
+        for (word, count) in words::top(counts, n) {
+            println!("{:>8} {}", count, word);
+        }
+        printed = true;
+    };
+    count_files(
+        files,
+        stdin_read,
+        |_, file| words::count_words(file, &rules),
+        |filename, result| match result {
+            Err(err) => eprintln!("{}", err),
+            Ok(counts) => {
+                if config.per_file {
+                    print(Some(&filename.display().to_string()), &counts);
+                }
+                words::merge(&mut total, counts);
+            }
+        },
+    );
+
+    if !config.per_file {
+        print(None, &total);
+    } else if files.len() > 1 {
+        print(Some("total"), &total);
     }
+    Ok(())
 }
 
-pub fn count(mut file: impl BufRead) -> MyResult<FileInfo> {
-    let mut num_lines = 0;
-    let mut num_words = 0;
-    let mut num_bytes = 0;
-    let mut num_chars = 0;
-    let mut line = String::new();
+// Count the files on several threads, reporting each in order as
+// soon as it and the files before it are done
+fn count_files<T: Send>(
+    files: &[PathBuf],
+    stdin_read: bool,
+    count: impl Fn(&Path, Box<dyn BufRead>) -> io::Result<T> + Sync,
+    mut report: impl FnMut(&Path, Result<T, String>),
+) {
+    let count_file = |filename: &Path| {
+        if stdin_read && filename.as_os_str() == "-" {
+            return Err("when reading file names from stdin, no file name \
+                        of '-' allowed"
+                .to_string());
+        }
+        open(filename)
+            .and_then(|file| Ok(count(filename, file)?))
+            .map_err(|e| format!("{}: {}", filename.display(), e))
+    };
+
+    let threads = thread::available_parallelism()
+        .map_or(1, NonZeroUsize::get)
+        .min(files.len());
+    if threads <= 1 {
+        for filename in files {
+            report(filename, count_file(filename));
+        }
+        return;
+    }
+
+    let next = &AtomicUsize::new(0);
+    let count_file = &count_file;
+    thread::scope(|scope| {
+        let (tx, rx) = mpsc::channel();
+        for _ in 0..threads {
+            let tx = tx.clone();
+            scope.spawn(move || loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                if i >= files.len()
+                    || tx.send((i, count_file(&files[i]))).is_err()
+                {
+                    break;
+                }
+            });
+        }
+        drop(tx);
 
+        let mut done = HashMap::new();
+        let mut reported = 0;
+        for (i, result) in rx {
+            done.insert(i, result);
+            while let Some(result) = done.remove(&reported) {
+                report(&files[reported], result);
+                reported += 1;
+            }
+        }
+    });
+}
+
+// The names in a list, skipping empty ones such as the one after a
+// trailing separator
+fn read_names(list: &NameList) -> MyResult<Vec<PathBuf>> {
+    let mut file = open(&list.file)
+        .map_err(|e| format!("{}: {}", list.file.display(), e))?;
+    let mut names = vec![];
+    let mut name = vec![];
     loop {
-        let line_bytes = file.read_line(&mut line)?;
-        if line_bytes == 0 {
+        name.clear();
+        let n = file
+            .read_until(list.separator, &mut name)
+            .map_err(|e| format!("{}: {}", list.file.display(), e))?;
+        if n == 0 {
             break;
         }
-        num_bytes += line_bytes;
-        num_lines += 1;
-        num_words += line.split_whitespace().count();
-        num_chars += line.chars().count();
-        line.clear();
+        if name.last() == Some(&list.separator) {
+            name.pop();
+        }
+        if !name.is_empty() {
+            names.push(path_from_bytes(&name));
+        }
     }
+    Ok(names)
+}
 
-    Ok(FileInfo { num_lines, num_words, num_bytes, num_chars })
+// Names are kept as they were written, even when they are not UTF-8
+#[cfg(unix)]
+fn path_from_bytes(name: &[u8]) -> PathBuf {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+    PathBuf::from(OsStr::from_bytes(name))
 }
 
-fn format_field(value: usize, show: bool) -> String {
-    if show {
-        format!("{:>8}", value)
+#[cfg(not(unix))]
+fn path_from_bytes(name: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(name).into_owned())
+}
+
+fn open(filename: &Path) -> MyResult<Box<dyn BufRead>> {
+    if filename.as_os_str() == "-" {
+        Ok(Box::new(BufReader::new(io::stdin())))
     } else {
-        "".to_string()
+        Ok(Box::new(BufReader::new(File::open(filename)?)))
+    }
+}
+
+/// Count everything in `file`
+pub fn count(file: impl Read) -> io::Result<FileInfo> {
+    count_with(file, Measure::ALL)
+}
+
+/// Count what `measure` asks for in `file`, reading it in large
+/// buffers so that it need not be buffered already
+pub fn count_with(
+    mut file: impl Read,
+    measure: Measure,
+) -> io::Result<FileInfo> {
+    let mut counter = Counter::new(measure);
+    let mut buffer = vec![0; BUFFER_SIZE];
+    loop {
+        match file.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => counter.update(&buffer[..n]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(counter.finish())
+}
+
+/// What a [`Counter`] measures besides lines and bytes, which cost
+/// next to nothing. Words, chars and widths need every character
+/// decoded, and Unicode words and graphemes need whole lines
+/// segmented, so leaving them out makes counting faster.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Measure {
+    pub words: bool,
+    pub chars: bool,
+    /// The maximum line length and line stats
+    pub widths: bool,
+    pub unicode_words: bool,
+    pub graphemes: bool,
+}
+
+impl Measure {
+    pub const ALL: Measure = Measure {
+        words: true,
+        chars: true,
+        widths: true,
+        unicode_words: true,
+        graphemes: true,
+    };
+}
+
+/// Counts a stream fed in byte slices of any size, keeping the
+/// state of the current word, line and UTF-8 sequence between them.
+///
+/// ```
+/// use wcr::{Counter, Measure};
+///
+/// let mut counter = Counter::new(Measure::ALL);
+/// counter.update(b"hello w");
+/// counter.update(b"orld\n\xe4\xb8");
+/// counter.update(b"\x96\xe7\x95\x8c\n");
+/// let info = counter.finish();
+/// assert_eq!(info.lines(), 2);
+/// assert_eq!(info.words(), 3);
+/// assert_eq!(info.unicode_words(), 4);
+/// assert_eq!(info.chars(), 15);
+/// assert_eq!(info.max_line_length(), 11);
+/// ```
+#[derive(Debug)]
+pub struct Counter {
+    measure: Measure,
+    info: FileInfo,
+    last_byte: Option<u8>,
+    in_word: bool,
+    partial: [u8; 4],
+    partial_len: usize,
+    column: usize,
+    line_width: usize,
+    short_lines: Vec<usize>,
+    line: Vec<u8>,
+}
+
+// Lines narrower than this are tallied in a plain array
+const SHORT_LINE: usize = 1024;
+
+impl Counter {
+    pub fn new(measure: Measure) -> Self {
+        Counter {
+            measure,
+            info: FileInfo::default(),
+            last_byte: None,
+            in_word: false,
+            partial: [0; 4],
+            partial_len: 0,
+            column: 0,
+            line_width: 0,
+            short_lines: vec![],
+            line: vec![],
+        }
+    }
+
+    /// Count the next part of the stream
+    pub fn update(&mut self, buf: &[u8]) {
+        if buf.is_empty() {
+            return;
+        }
+        self.info.num_bytes += buf.len();
+        self.info.num_lines += memchr_iter(b'\n', buf).count();
+        if self.measure.chars || self.measure.words || self.measure.widths {
+            self.scan(buf);
+        }
+        if self.measure.unicode_words || self.measure.graphemes {
+            self.segment(buf);
+        }
+        self.last_byte = buf.last().copied();
+    }
+
+    // Neither Unicode words nor graphemes span a newline, as a CR LF
+    // pair ends at one, so they are counted a line at a time
+    fn segment(&mut self, buf: &[u8]) {
+        let mut start = 0;
+        for end in memchr_iter(b'\n', buf) {
+            self.line.extend_from_slice(&buf[start..=end]);
+            self.segment_line();
+            start = end + 1;
+        }
+        self.line.extend_from_slice(&buf[start..]);
+    }
+
+    // Bytes that are not valid UTF-8 are neither words nor graphemes,
+    // and break up any that they come between
+    fn segment_line(&mut self) {
+        for chunk in self.line.utf8_chunks() {
+            let text = chunk.valid();
+            if self.measure.unicode_words {
+                self.info.num_unicode_words += text.unicode_words().count();
+            }
+            if self.measure.graphemes {
+                self.info.num_graphemes += text.graphemes(true).count();
+            }
+        }
+        self.line.clear();
+    }
+
+    fn scan(&mut self, buf: &[u8]) {
+        let mut i = 0;
+
+        // Finish a sequence split across buffers. The stashed bytes
+        // are a lead byte and continuation bytes, so if the sequence
+        // turns out to be invalid none of them can start a character.
+        if self.partial_len > 0 {
+            let len = utf8_len(self.partial[0]);
+            while self.partial_len < len && i < buf.len() && is_cont(buf[i]) {
+                self.partial[self.partial_len] = buf[i];
+                self.partial_len += 1;
+                i += 1;
+            }
+            if self.partial_len < len && i == buf.len() {
+                return;
+            }
+            let partial = self.partial;
+            self.visit_bytes(&partial[..self.partial_len]);
+            self.partial_len = 0;
+        }
+
+        while i < buf.len() {
+            i += self.scan_ascii(&buf[i..]);
+            if i == buf.len() {
+                break;
+            }
+
+            let len = utf8_len(buf[i]);
+            let rest = &buf[i..];
+            if rest.len() < len && rest[1..].iter().all(|&b| is_cont(b)) {
+                self.partial[..rest.len()].copy_from_slice(rest);
+                self.partial_len = rest.len();
+                break;
+            }
+            i += self.visit_bytes(&rest[..len.min(rest.len())]);
+        }
+    }
+
+    // Visit the ASCII bytes at the start of `buf` and return how many
+    // there were. Most text is ASCII, so words alone get a tight loop.
+    fn scan_ascii(&mut self, buf: &[u8]) -> usize {
+        let len = ascii_len(buf);
+        let ascii = &buf[..len];
+        if self.measure.chars {
+            self.info.num_chars += len;
+        }
+        if self.measure.widths {
+            for &b in ascii {
+                if self.measure.words {
+                    self.count_word(is_ascii_space(b));
+                }
+                let width = usize::from(matches!(b, b' '..=b'~'));
+                self.advance(char::from(b), width);
+            }
+        } else if self.measure.words {
+            let mut in_word = self.in_word;
+            let mut words = 0;
+            for &b in ascii {
+                let is_space = is_ascii_space(b);
+                words += usize::from(!is_space && !in_word);
+                in_word = !is_space;
+            }
+            self.in_word = in_word;
+            self.info.num_words += words;
+        }
+        len
     }
+
+    // Visit the character that `bytes` encode and return its length.
+    // Like `wc -m`, a byte that is not part of a valid sequence is not
+    // a character and takes no columns, but it is not whitespace
+    // either, so it still belongs to a word. Decoding carries on at
+    // the next byte, which finds the same characters as
+    // `String::from_utf8_lossy`.
+    fn visit_bytes(&mut self, bytes: &[u8]) -> usize {
+        match decode_utf8(bytes) {
+            Some(c) => {
+                if self.measure.chars {
+                    self.info.num_chars += 1;
+                }
+                if self.measure.words {
+                    self.count_word(c.is_whitespace());
+                }
+                if self.measure.widths {
+                    self.advance(c, c.width().unwrap_or(0));
+                }
+                bytes.len()
+            }
+            None => {
+                if self.measure.words {
+                    self.count_word(false);
+                }
+                1
+            }
+        }
+    }
+
+    fn count_word(&mut self, is_space: bool) {
+        if !is_space && !self.in_word {
+            self.info.num_words += 1;
+        }
+        self.in_word = !is_space;
+    }
+
+    // Display widths follow `wc -L`: tabs move to the next multiple
+    // of 8, wide characters take two columns and nonprinting ones
+    // none, and a carriage return or form feed starts over at the
+    // first column
+    #[inline]
+    fn advance(&mut self, c: char, width: usize) {
+        match c {
+            '\n' => self.end_line(),
+            '\r' | '\x0c' => {
+                self.line_width = self.line_width.max(self.column);
+                self.column = 0;
+            }
+            '\t' => self.column += 8 - self.column % 8,
+            _ => self.column += width,
+        }
+    }
+
+    fn end_line(&mut self) {
+        let width = self.line_width.max(self.column);
+        self.info.max_line_length = self.info.max_line_length.max(width);
+        if width < SHORT_LINE {
+            if self.short_lines.is_empty() {
+                self.short_lines = vec![0; SHORT_LINE];
+            }
+            self.short_lines[width] += 1;
+        } else {
+            self.info.line_stats.add(width);
+        }
+        self.column = 0;
+        self.line_width = 0;
+    }
+
+    /// The counts for the whole stream. A sequence cut short by the
+    /// end of the stream is invalid, and a last line without a
+    /// newline still counts as a line.
+    pub fn finish(mut self) -> FileInfo {
+        if self.partial_len > 0 && self.measure.words {
+            self.count_word(false);
+        }
+        if !self.line.is_empty() {
+            self.segment_line();
+        }
+        if self.last_byte.is_some_and(|b| b != b'\n') {
+            self.info.num_lines += 1;
+            if self.measure.widths {
+                self.end_line();
+            }
+        }
+        for (width, &n) in self.short_lines.iter().enumerate() {
+            if n > 0 {
+                *self.info.line_stats.widths.entry(width).or_default() += n;
+            }
+        }
+        self.info
+    }
+}
+
+// The ASCII characters that `char::is_whitespace` accepts
+fn is_ascii_space(b: u8) -> bool {
+    matches!(b, b'\t'..=b'\r' | b' ')
+}
+
+// The length of the ASCII run at the start of `buf`, checked a word
+// at a time
+fn ascii_len(buf: &[u8]) -> usize {
+    const HIGH_BITS: u64 = 0x8080_8080_8080_8080;
+    let mut len = 0;
+    for chunk in buf.chunks_exact(8) {
+        let word = u64::from_ne_bytes(chunk.try_into().unwrap());
+        if word & HIGH_BITS != 0 {
+            break;
+        }
+        len += 8;
+    }
+    len + buf[len..].iter().take_while(|b| b.is_ascii()).count()
+}
+
+// Whether a byte continues a UTF-8 sequence
+fn is_cont(b: u8) -> bool {
+    b & 0xc0 == 0x80
+}
+
+// The character encoded by a whole UTF-8 sequence, rejecting what
+// `str::from_utf8` would: overlong forms, surrogates and values
+// past U+10FFFF
+fn decode_utf8(bytes: &[u8]) -> Option<char> {
+    if bytes.first().map(|&b| utf8_len(b)) != Some(bytes.len()) {
+        return None;
+    }
+    let cont = |b: u8| u32::from(b & 0x3f);
+    let (value, min) = match *bytes {
+        [a, b] if is_cont(b) => (u32::from(a & 0x1f) << 6 | cont(b), 0x80),
+        [a, b, c] if is_cont(b) && is_cont(c) => {
+            (u32::from(a & 0x0f) << 12 | cont(b) << 6 | cont(c), 0x800)
+        }
+        [a, b, c, d] if is_cont(b) && is_cont(c) && is_cont(d) => (
+            u32::from(a & 0x07) << 18 | cont(b) << 12 | cont(c) << 6 | cont(d),
+            0x10000,
+        ),
+        _ => return None,
+    };
+    (value >= min).then(|| char::from_u32(value)).flatten()
+}
+
+// The length of the UTF-8 sequence a byte starts, or 1 for a byte
+// that cannot start one
+fn utf8_len(b: u8) -> usize {
+    match b {
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => 1,
+    }
+}
+
+// The values selected for a row, in column order
+fn fields(info: &FileInfo, config: &Config) -> Vec<String> {
+    let counts = [
+        (info.num_lines, config.lines),
+        (info.num_words, config.words),
+        (info.num_unicode_words, config.unicode_words),
+        (info.num_bytes, config.bytes),
+        (info.num_chars, config.chars),
+        (info.num_graphemes, config.graphemes),
+        (info.max_line_length, config.max_line_length),
+    ];
+    let mut fields: Vec<String> = counts
+        .iter()
+        .filter(|(_, show)| *show)
+        .map(|(value, _)| value.to_string())
+        .collect();
+    if config.line_stats {
+        fields.extend(stats_fields(&info.line_stats));
+    }
+    fields
+}
+
+// Min, mean, median and p95, or dashes when there are no lines
+fn stats_fields(stats: &LineStats) -> [String; 4] {
+    match (stats.min(), stats.mean()) {
+        (Some(min), Some(mean)) => [
+            min.to_string(),
+            format!("{:.1}", mean),
+            stats.percentile(50).unwrap_or(min).to_string(),
+            stats.percentile(95).unwrap_or(min).to_string(),
+        ],
+        _ => ["-", "-", "-", "-"].map(String::from),
+    }
+}
+
+// Columns are as wide as BSD wc makes them, or wider to keep a space
+// before the widest value
+const MIN_WIDTH: usize = 8;
+
+fn column_width<'a>(fields: impl Iterator<Item = &'a String>) -> usize {
+    fields.map(|field| field.len() + 1).fold(MIN_WIDTH, usize::max)
+}
+
+fn format_field(value: &str, width: usize) -> String {
+    format!("{:>width$}", value)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{count, format_field, FileInfo};
-    use std::io::Cursor;
+    use super::{
+        column_width, count, count_with, decode_utf8, format_field, Counter,
+        FileInfo, LineStats, Measure,
+    };
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use std::io::{self, BufRead, Cursor, Read};
+    use unicode_segmentation::UnicodeSegmentation;
+    use unicode_width::UnicodeWidthChar;
+
+    // The line-at-a-time implementation that the byte engine replaced,
+    // kept as a reference for what it should count
+    fn count_by_lines(mut file: impl BufRead) -> io::Result<FileInfo> {
+        let mut info = FileInfo::default();
+        let mut line = String::new();
+        loop {
+            let line_bytes = file.read_line(&mut line)?;
+            if line_bytes == 0 {
+                break;
+            }
+            info.num_bytes += line_bytes;
+            info.num_lines += 1;
+            info.num_words += line.split_whitespace().count();
+            info.num_unicode_words += line.unicode_words().count();
+            info.num_chars += line.chars().count();
+            info.num_graphemes += line.graphemes(true).count();
+            let width = line_width(&line);
+            info.max_line_length = info.max_line_length.max(width);
+            info.line_stats.add(width);
+            line.clear();
+        }
+        Ok(info)
+    }
+
+    fn line_width(line: &str) -> usize {
+        let mut width = 0;
+        let mut column = 0;
+        for c in line.chars() {
+            match c {
+                '\n' | '\r' | '\x0c' => {
+                    width = width.max(column);
+                    column = 0;
+                }
+                '\t' => column += 8 - column % 8,
+                _ => column += c.width().unwrap_or(0),
+            }
+        }
+        width.max(column)
+    }
+
+    fn max_line_length(text: &str) -> usize {
+        count(Cursor::new(text)).unwrap().max_line_length
+    }
+
+    // A reader that hands out a few bytes at a time, so that lines,
+    // words and characters are split between reads
+    struct Trickle<'a> {
+        data: &'a [u8],
+        rng: StdRng,
+    }
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n =
+                self.rng.gen_range(1..=7).min(buf.len()).min(self.data.len());
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            Ok(n)
+        }
+    }
+
+    fn random_text(rng: &mut StdRng, len: usize) -> String {
+        const PIECES: [&str; 20] = [
+            "a",
+            "word",
+            " ",
+            "  ",
+            "\t",
+            "\n",
+            "\r\n",
+            "\x0c",
+            "\x0b",
+            "é",
+            "e\u{301}",
+            "日本",
+            "\u{a0}",
+            "\u{2003}",
+            "\u{3000}",
+            "🦀",
+            "can't",
+            "3.14",
+            "👨\u{200d}👩",
+            "🇯🇵",
+        ];
+        (0..len).map(|_| PIECES[rng.gen_range(0..PIECES.len())]).collect()
+    }
 
     #[test]
     fn test_count() {
         let text = "I don't want the world. I just want your half.\r\n";
         let info = count(Cursor::new(text));
         assert!(info.is_ok());
+        let mut line_stats = LineStats::default();
+        line_stats.add(46);
         let expected = FileInfo {
             num_lines: 1,
             num_words: 10,
+            num_unicode_words: 10,
             num_chars: 48,
+            num_graphemes: 47,
             num_bytes: 48,
+            max_line_length: 46,
+            line_stats,
+            ..Default::default()
         };
         assert_eq!(info.unwrap(), expected);
+
+        // Without spaces between words, or with characters made of
+        // several code points, words and chars tell only part of it
+        for (text, words, unicode_words, chars, graphemes) in [
+            ("日本語のテキスト", 1, 5, 8, 8),
+            ("Grüße aus Köln", 3, 3, 14, 14),
+            ("Cafe\u{301} nai\u{308}ve", 2, 2, 12, 10),
+            ("Привет, мир!", 2, 2, 12, 12),
+            ("किताब पढ़ो", 2, 2, 10, 6),
+            ("👨\u{200d}👩\u{200d}👧 family 🇯🇵", 3, 1, 15, 10),
+        ] {
+            let info = count(Cursor::new(text)).unwrap();
+            assert_eq!(info.num_words, words, "{}", text);
+            assert_eq!(info.num_unicode_words, unicode_words, "{}", text);
+            assert_eq!(info.num_chars, chars, "{}", text);
+            assert_eq!(info.num_graphemes, graphemes, "{}", text);
+        }
+    }
+
+    // Words and graphemes come out the same wherever the text is split
+    // between reads, even inside a grapheme or a UTF-8 sequence
+    #[test]
+    fn test_segmentation() {
+        for (text, unicode_words, graphemes) in [
+            ("กำลัง", 4, 3),
+            ("ภาษาไทย ง่าย", 10, 11),
+            ("किताब पढ़ो", 2, 6),
+            ("ক্ষমা", 1, 2),
+            ("\u{600}1", 1, 1),
+            ("\u{600}\u{600}a b", 2, 3),
+            ("👨\u{200d}👩\u{200d}👧 family 🇯🇵", 1, 10),
+            ("🏳\u{fe0f}\u{200d}🌈 x²", 2, 4),
+            ("x²", 2, 2),
+        ] {
+            let bytes = text.as_bytes();
+            for split in 0..=bytes.len() {
+                let mut counter = Counter::new(Measure::ALL);
+                counter.update(&bytes[..split]);
+                counter.update(&bytes[split..]);
+                let info = counter.finish();
+                assert_eq!(info.num_unicode_words, unicode_words, "{}", text);
+                assert_eq!(info.num_graphemes, graphemes, "{}", text);
+            }
+        }
+    }
+
+    #[test]
+    fn test_add_file_info() {
+        let a = count(Cursor::new("one two\nthree\n")).unwrap();
+        let b = count(Cursor::new("a much longer line\n")).unwrap();
+        let total = a.clone() + b.clone();
+        assert_eq!((total.lines(), total.words(), total.bytes()), (3, 7, 33));
+        assert_eq!(total.max_line_length(), 18);
+        assert_eq!(total.line_stats().min(), Some(5));
+        assert_eq!(total.line_stats().percentile(100), Some(18));
+        assert_eq!([&a, &b].into_iter().sum::<FileInfo>(), total);
+        assert_eq!([a, b].into_iter().sum::<FileInfo>(), total);
+        assert_eq!(
+            Vec::<FileInfo>::new().into_iter().sum::<FileInfo>(),
+            FileInfo::default()
+        );
+
+        // Only what was asked for is counted
+        let measure = Measure { words: true, ..Default::default() };
+        let mut counter = Counter::new(measure);
+        for byte in b"one two\nthree" {
+            counter.update(&[*byte]);
+        }
+        let info = counter.finish();
+        assert_eq!((info.lines(), info.words(), info.bytes()), (2, 3, 13));
+        assert_eq!((info.chars(), info.max_line_length()), (0, 0));
+    }
+
+    #[test]
+    fn test_max_line_length() {
+        assert_eq!(max_line_length(""), 0);
+        assert_eq!(max_line_length("abc\n"), 3);
+        assert_eq!(max_line_length("\tx"), 9);
+        assert_eq!(max_line_length("abcdefgh\tx"), 17);
+        assert_eq!(max_line_length("ab\tc\td"), 17);
+        assert_eq!(max_line_length("日本語"), 6);
+        assert_eq!(max_line_length("e\u{301}"), 1);
+        assert_eq!(max_line_length("long line\rshort"), 9);
+        assert_eq!(max_line_length("\x07bell"), 4);
+        assert_eq!(max_line_length("ab\n\tindented\nc"), 16);
+    }
+
+    #[test]
+    fn test_decode_utf8() {
+        for c in ['\u{80}', 'é', '\u{7ff}', '\u{800}', '日', '\u{ffff}', '🦀']
+        {
+            let mut buf = [0; 4];
+            assert_eq!(
+                decode_utf8(c.encode_utf8(&mut buf).as_bytes()),
+                Some(c)
+            );
+        }
+        assert_eq!(decode_utf8(&[0xc0, 0x80]), None);
+        assert_eq!(decode_utf8(&[0xe0, 0x80, 0x80]), None);
+        assert_eq!(decode_utf8(&[0xed, 0xa0, 0x80]), None);
+        assert_eq!(decode_utf8(&[0xf4, 0x90, 0x80, 0x80]), None);
+        assert_eq!(decode_utf8(&[0xc3, 0x28]), None);
+        assert_eq!(decode_utf8(&[0xe6, 0x97]), None);
+        assert_eq!(decode_utf8(&[0xfc, 0x80, 0x80, 0x80]), None);
+        assert_eq!(decode_utf8(&[0x80]), None);
+    }
+
+    #[test]
+    fn test_count_matches_lines() {
+        let mut rng = StdRng::seed_from_u64(39);
+        for len in [0, 1, 2, 5, 50, 1000, 100_000] {
+            let text = random_text(&mut rng, len);
+            let expected = count_by_lines(Cursor::new(&text)).unwrap();
+            assert_eq!(count(Cursor::new(&text)).unwrap(), expected);
+
+            let trickle = Trickle {
+                data: text.as_bytes(),
+                rng: StdRng::seed_from_u64(len as u64),
+            };
+            assert_eq!(count_with(trickle, Measure::ALL).unwrap(), expected);
+        }
+    }
+
+    // The lossy decoding finds the same characters, where each
+    // replacement character stands for bytes that belong to a word
+    // but are not characters themselves, nor Unicode words or
+    // graphemes
+    fn count_lossy(bytes: &[u8]) -> FileInfo {
+        let lossy = String::from_utf8_lossy(bytes);
+        let stripped = lossy.replace('\u{fffd}', "");
+        let words = count_by_lines(Cursor::new(lossy.as_bytes())).unwrap();
+        let mut info = count_by_lines(Cursor::new(stripped)).unwrap();
+        info.num_words = words.num_words;
+        info.num_unicode_words = words.num_unicode_words;
+        info.num_graphemes =
+            words.num_graphemes - lossy.matches('\u{fffd}').count();
+        info.num_bytes = bytes.len();
+        info
+    }
+
+    #[test]
+    fn test_count_invalid_utf8() {
+        let info = count(Cursor::new(b"caf\xe9 au lait\n\xff\xfe\n")).unwrap();
+        assert_eq!(info.num_lines, 2);
+        assert_eq!(info.num_words, 4);
+        assert_eq!(info.num_bytes, 16);
+        assert_eq!(info.num_chars, 13);
+        assert_eq!(info.max_line_length, 11);
+
+        // A truncated sequence does not swallow the bytes after it
+        let info = count(Cursor::new(b"\xe6\x97a\xf0\x9f\xa6 b\xe6")).unwrap();
+        assert_eq!((info.num_words, info.num_chars), (2, 3));
+
+        const PIECES: [&[u8]; 8] = [
+            b"\xff",
+            b"\x80",
+            b"\xc3",
+            b"\xe6\x97",
+            b"\xf0\x9f\xa6",
+            b"\xed\xa0\x80",
+            b"\xc0\xaf",
+            b"\xf8\x88\x80\x80",
+        ];
+        let mut rng = StdRng::seed_from_u64(40);
+        for len in [1, 2, 5, 50, 1000, 100_000] {
+            let mut bytes = vec![];
+            for _ in 0..len {
+                if rng.gen_bool(0.3) {
+                    bytes.extend(PIECES[rng.gen_range(0..PIECES.len())]);
+                } else {
+                    bytes.extend(random_text(&mut rng, 1).as_bytes());
+                }
+            }
+            let expected = count_lossy(&bytes);
+            assert_eq!(count(Cursor::new(&bytes)).unwrap(), expected);
+
+            let trickle = Trickle {
+                data: &bytes,
+                rng: StdRng::seed_from_u64(len as u64),
+            };
+            assert_eq!(count_with(trickle, Measure::ALL).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_line_stats() {
+        let mut stats = LineStats::default();
+        assert_eq!(stats.min(), None);
+        assert_eq!(stats.mean(), None);
+        assert_eq!(stats.percentile(50), None);
+
+        for width in 1..=20 {
+            stats.add(width);
+        }
+        assert_eq!(stats.min(), Some(1));
+        assert_eq!(stats.mean(), Some(10.5));
+        assert_eq!(stats.percentile(50), Some(10));
+        assert_eq!(stats.percentile(95), Some(19));
+
+        let mut other = LineStats::default();
+        other.add(100);
+        stats.merge(&other);
+        assert_eq!(stats.percentile(95), Some(20));
+        assert_eq!(stats.percentile(100), Some(100));
     }
 
     #[test]
     fn test_format_field() {
-        assert_eq!(format_field(1, false), "");
-        assert_eq!(format_field(3, true), "       3");
-        assert_eq!(format_field(10, true), "      10");
+        assert_eq!(format_field("3", 8), "       3");
+        assert_eq!(format_field("10", 8), "      10");
+        assert_eq!(format_field("123456789", 10), " 123456789");
+    }
+
+    #[test]
+    fn test_column_width() {
+        let fields = ["1", "1234567"].map(String::from);
+        assert_eq!(column_width(fields.iter()), 8);
+        let fields = ["1", "123456789012"].map(String::from);
+        assert_eq!(column_width(fields.iter()), 13);
+        assert_eq!(column_width([].iter()), 8);
     }
 }
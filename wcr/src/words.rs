@@ -0,0 +1,103 @@
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    io::{self, BufRead},
+};
+use unicode_segmentation::UnicodeSegmentation;
+
+// How words are found and compared for `--top-words`
+#[derive(Debug, Default)]
+pub struct WordRules {
+    pub unicode: bool,
+    pub ignore_case: bool,
+    pub stopwords: HashSet<String>,
+}
+
+impl WordRules {
+    // Stopwords are folded like the words they are compared with
+    pub fn add_stopwords(&mut self, text: &str) {
+        for word in text.split_whitespace() {
+            let word = self.fold(word).into_owned();
+            self.stopwords.insert(word);
+        }
+    }
+
+    fn fold<'a>(&self, word: &'a str) -> Cow<'a, str> {
+        if self.ignore_case && word.chars().any(char::is_uppercase) {
+            word.to_lowercase().into()
+        } else {
+            word.into()
+        }
+    }
+}
+
+pub type WordCounts = HashMap<String, usize>;
+
+// Tally the words in a file. Lines are decoded lossily, so bytes
+// that are not valid UTF-8 show up as U+FFFD within a word, just as
+// `count` takes them for part of one.
+pub fn count_words(
+    mut file: impl BufRead,
+    rules: &WordRules,
+) -> io::Result<WordCounts> {
+    let mut counts = WordCounts::new();
+    let mut line = vec![];
+    while file.read_until(b'\n', &mut line)? > 0 {
+        let text = String::from_utf8_lossy(&line);
+        let mut add = |word: &str| {
+            let word = rules.fold(word);
+            if rules.stopwords.contains(word.as_ref()) {
+                return;
+            }
+            match counts.get_mut(word.as_ref()) {
+                Some(n) => *n += 1,
+                None => {
+                    counts.insert(word.into_owned(), 1);
+                }
+            }
+        };
+        if rules.unicode {
+            text.unicode_words().for_each(&mut add);
+        } else {
+            text.split_whitespace().for_each(&mut add);
+        }
+        line.clear();
+    }
+    Ok(counts)
+}
+
+pub fn merge(total: &mut WordCounts, counts: WordCounts) {
+    for (word, n) in counts {
+        *total.entry(word).or_default() += n;
+    }
+}
+
+// The `n` most frequent words, ties going in alphabetical order
+pub fn top(counts: &WordCounts, n: usize) -> Vec<(&str, usize)> {
+    let mut ranked: Vec<_> =
+        counts.iter().map(|(word, &count)| (word.as_str(), count)).collect();
+    ranked.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    ranked.truncate(n);
+    ranked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{count_words, top, WordRules};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_count_words() {
+        let mut rules = WordRules { ignore_case: true, ..Default::default() };
+        rules.add_stopwords("A\n");
+        let text = "The cat and THE hat\na cat\n";
+        let counts = count_words(Cursor::new(text), &rules).unwrap();
+        assert_eq!(top(&counts, 3), [("cat", 2), ("the", 2), ("and", 1)]);
+        assert_eq!(counts.get("a"), None);
+
+        let rules = WordRules::default();
+        let counts =
+            count_words(Cursor::new(b"x\xff y x\xff"), &rules).unwrap();
+        assert_eq!(top(&counts, 5), [("x\u{fffd}", 2), ("y", 1)]);
+    }
+}
@@ -1,3 +1,5 @@
+#![allow(clippy::needless_borrows_for_generic_args)]
+
 use assert_cmd::Command;
 use predicates::prelude::*;
 use rand::{distributions::Alphanumeric, Rng};
@@ -9,6 +11,11 @@ const PRG: &str = "wcr";
 const EMPTY: &str = "tests/inputs/empty.txt";
 const FOX: &str = "tests/inputs/fox.txt";
 const ATLAMAL: &str = "tests/inputs/atlamal.txt";
+const WIDE: &str = "tests/inputs/wide.txt";
+const INVALID: &str = "tests/inputs/invalid.txt";
+const FILES0: &str = "tests/inputs/files0.txt";
+const CODE: &str = "tests/inputs/code";
+const MULTILINGUAL: &str = "tests/inputs/multilingual.txt";
 
 // --------------------------------------------------
 fn gen_bad_file() -> String {
@@ -56,6 +63,18 @@ fn skips_bad_file() -> TestResult {
     Ok(())
 }
 
+// --------------------------------------------------
+#[test]
+fn reports_read_error() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs", FOX])
+        .assert()
+        .success()
+        .stderr(predicate::str::starts_with("tests/inputs: "))
+        .stdout(predicate::str::contains(FOX));
+    Ok(())
+}
+
 // --------------------------------------------------
 #[test]
 fn empty() -> TestResult {
@@ -202,3 +221,301 @@ fn test_all_words_lines() -> TestResult {
 fn test_all_bytes_lines() -> TestResult {
     run(&["-cl", EMPTY, FOX, ATLAMAL], "tests/expected/all.cl.out")
 }
+
+// --------------------------------------------------
+#[test]
+fn atlamal_max_line_length() -> TestResult {
+    run(&["--max-line-length", ATLAMAL], "tests/expected/atlamal.txt.L.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn wide() -> TestResult {
+    run(&[WIDE], "tests/expected/wide.txt.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn wide_max_line_length() -> TestResult {
+    run(&["-L", WIDE], "tests/expected/wide.txt.L.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn wide_line_stats() -> TestResult {
+    run(&["--line-stats", WIDE], "tests/expected/wide.txt.linestats.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_max_line_length() -> TestResult {
+    run(&["-L", EMPTY, FOX, ATLAMAL], "tests/expected/all.L.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_line_stats() -> TestResult {
+    run(
+        &["-lL", "--line-stats", EMPTY, FOX, ATLAMAL, WIDE],
+        "tests/expected/all.lLlinestats.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn invalid_utf8() -> TestResult {
+    run(&[INVALID], "tests/expected/invalid.txt.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn invalid_utf8_chars() -> TestResult {
+    run(&["-m", INVALID], "tests/expected/invalid.txt.m.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn invalid_utf8_max_line_length() -> TestResult {
+    run(&["-L", INVALID], "tests/expected/invalid.txt.L.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_files0_from() -> TestResult {
+    run(&["--files0-from", FILES0], "tests/expected/all.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_files_from_stdin() -> TestResult {
+    let names = [EMPTY, FOX, ATLAMAL].join("\n") + "\n";
+    let expected = fs::read_to_string("tests/expected/all.out")?;
+    Command::cargo_bin(PRG)?
+        .args(["--files-from", "-"])
+        .write_stdin(names)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn files_from_many_names() -> TestResult {
+    let names = format!("{}\0", FOX).repeat(20_000);
+    Command::cargo_bin(PRG)?
+        .args(["-l", "--files0-from=-"])
+        .write_stdin(names)
+        .assert()
+        .success()
+        .stdout(predicate::str::ends_with("   20000 total\n"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn files_from_stdin_rejects_dash() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--files-from", "-"])
+        .write_stdin(format!("{}\n-\n", FOX))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(FOX))
+        .stderr(predicate::str::contains("no file name of '-' allowed"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(unix)]
+fn files_from_non_utf8_name() -> TestResult {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+    let dir = std::env::temp_dir().join(format!("wcr-{}", gen_bad_file()));
+    fs::create_dir(&dir)?;
+    let path = dir.join(OsStr::from_bytes(b"caf\xe9.txt"));
+    fs::write(&path, "one two\n")?;
+    let mut names = path.as_os_str().as_bytes().to_vec();
+    names.push(0);
+
+    let output = Command::cargo_bin(PRG)?
+        .args(["-w", "--files0-from=-"])
+        .write_stdin(names)
+        .output()?;
+    fs::remove_dir_all(&dir)?;
+    assert!(output.status.success());
+    assert!(output.stdout.starts_with(b"       2 "));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_files_from_and_files() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--files0-from", FILES0, FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn code() -> TestResult {
+    run(&["--code", CODE], "tests/expected/code.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn code_exclude() -> TestResult {
+    run(
+        &[
+            "--code",
+            "--exclude",
+            "vendor",
+            "--exclude",
+            "tests/inputs/code/src/*.py",
+            CODE,
+        ],
+        "tests/expected/code.exclude.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn code_total_only() -> TestResult {
+    run(
+        &["--code", "--total", "only", CODE],
+        "tests/expected/code.total_only.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn code_single_language() -> TestResult {
+    let main = "tests/inputs/code/src/main.rs";
+    run(&["--code", main], "tests/expected/code.single.out")?;
+    run(
+        &["--code", "--total", "always", main],
+        "tests/expected/code.single.total_always.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_exclude() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--code", "--exclude", "a[", CODE])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid --exclude \"a[\""));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_code_and_lines() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--code", "-l", CODE])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn top_words() -> TestResult {
+    run(&["--top-words", "5", FOX, ATLAMAL], "tests/expected/all.top5.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn top_words_per_file() -> TestResult {
+    run(
+        &["--top-words", "3", "--per-file", FOX, ATLAMAL],
+        "tests/expected/all.top3.perfile.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn top_words_unicode_folded() -> TestResult {
+    run(
+        &[
+            "--top-words",
+            "5",
+            "--ignore-case",
+            "--unicode-words",
+            "--stopwords",
+            "tests/inputs/stopwords.txt",
+            FOX,
+            ATLAMAL,
+        ],
+        "tests/expected/all.top5.unicode.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_top_words() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--top-words", "0", FOX])
+        .assert()
+        .failure()
+        .stderr("illegal word count -- 0\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn multilingual_graphemes() -> TestResult {
+    run(
+        &["--graphemes", MULTILINGUAL],
+        "tests/expected/multilingual.txt.graphemes.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn multilingual_unicode_words() -> TestResult {
+    run(
+        &["-wm", "--unicode-words", "--graphemes", MULTILINGUAL, FOX],
+        "tests/expected/multilingual.unicode.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn fox_total_always() -> TestResult {
+    run(&["--total=always", FOX], "tests/expected/fox.txt.total_always.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_total_only() -> TestResult {
+    run(
+        &["--total", "only", EMPTY, FOX, ATLAMAL],
+        "tests/expected/all.total_only.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_total_never() -> TestResult {
+    run(
+        &["--total", "never", EMPTY, FOX, ATLAMAL],
+        "tests/expected/all.total_never.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_total() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--total", "sometimes", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("isn't a valid value"));
+    Ok(())
+}
@@ -0,0 +1,12 @@
+// A line comment
+/* A block comment /* nested */
+   still inside */
+
+fn main() {
+    let url = "http://example.com/*"; // not a block
+    /* inline */ println!("{}", url);
+
+    /*
+     * multi-line
+     */
+}
@@ -0,0 +1,3 @@
+-- Report
+SELECT 1; /* start
+end */
@@ -0,0 +1,8 @@
+#!/usr/bin/env python3
+# Helpers
+
+def greet(name):
+    return "# not a comment " + name  # trailing
+
+
+print(greet("x"))